@@ -0,0 +1,60 @@
+//! Compare `step_state` against the bit-packed stepper
+//!
+//! Run with `cd life && cargo bench --target x86_64-unknown-linux-gnu`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use life::packed::Row;
+use life::{step_state, Grid, PackedGrid, Rng, Rule, Topology};
+
+const GENERATIONS: u32 = 2000;
+
+/// Fill a grid with a deterministic mess of live cells
+fn soup<const W: usize, const H: usize>() -> Grid<W, H> {
+    let mut state = [[0; W]; H];
+    let mut rng = Rng::new(0x2545_f491);
+    for cell in state.iter_mut().flatten() {
+        *cell = (rng.next_u32() & 3 == 0) as u8;
+    }
+    state
+}
+
+fn time(mut step: impl FnMut()) -> Duration {
+    // warm up first so both sides start with hot caches
+    for _ in 0..GENERATIONS / 10 {
+        step();
+    }
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        step();
+    }
+    start.elapsed() / GENERATIONS
+}
+
+fn compare<T: Row, const W: usize, const H: usize>(topology: Topology) {
+    let rule = Rule::CONWAY;
+
+    let mut state: Grid<W, H> = soup();
+    let bytes = time(|| step_state(black_box(&mut state), &rule, &topology));
+
+    let mut packed = PackedGrid::<T, W, H>::from_grid(&soup());
+    let bits = time(|| black_box(&mut packed).step(&rule, &topology).unwrap());
+
+    println!(
+        "{:>3}x{:<3} {:<6} step_state {:>9.2?}  packed {:>9.2?}  speedup {:>5.1}x",
+        W,
+        H,
+        format!("{:?}", topology),
+        bytes,
+        bits,
+        bytes.as_secs_f64() / bits.as_secs_f64()
+    );
+}
+
+fn main() {
+    compare::<u16, 16, 8>(Topology::Torus);
+    compare::<u16, 16, 8>(Topology::Plane);
+    compare::<u32, 32, 16>(Topology::Torus);
+    compare::<u64, 64, 32>(Topology::Torus);
+    compare::<u64, 64, 32>(Topology::Plane);
+}
@@ -0,0 +1,146 @@
+//! How long cells have been alive, and brightness that changes with age
+//!
+//! Every byte of the state is already taken while stepping, the low nibble
+//! holding the state and the high nibble the next one, so ages are kept in a
+//! grid of their own. After each step, `step_ages` counts live cells one
+//! generation older and resets everything else to 0. A cell that has just
+//! been born is 1, and ages stop at 255.
+//!
+//! Since only live cells have an age, the age grid can be shown on its own
+//! with an `AgeGradient` as the palette. Dying cells in Generations rules
+//! count as dead.
+use crate::{Grid, Palette, STATE};
+
+/// Brightness that moves from one level to another as cells get older
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AgeGradient {
+    /// The brightness of a cell that has just been born
+    pub newborn: u8,
+    /// The brightness cells settle at
+    pub settled: u8,
+    /// How many generations it takes to get from `newborn` to `settled`
+    pub settle_after: u8,
+}
+
+impl AgeGradient {
+    /// Newborn cells flash bright and fade to a dim glow, so anything still
+    /// is dim and anything moving is bright
+    pub const FLASH: AgeGradient = AgeGradient::new(15, 3, 6);
+    /// Newborn cells start dim and brighten as they last, so still lifes
+    /// stand out
+    pub const GROW: AgeGradient = AgeGradient::new(3, 15, 12);
+    /// Every live cell at full brightness, however old it is
+    pub const FLAT: AgeGradient = AgeGradient::new(15, 15, 1);
+
+    /// A gradient, panicking if a brightness is past 15 or it settles
+    /// straight away
+    pub const fn new(newborn: u8, settled: u8, settle_after: u8) -> AgeGradient {
+        if newborn > 15 || settled > 15 || settle_after == 0 {
+            panic!("invalid age gradient");
+        }
+        AgeGradient {
+            newborn,
+            settled,
+            settle_after,
+        }
+    }
+}
+
+impl Palette for AgeGradient {
+    /// The brightness for a cell's age, with dead cells dark
+    fn brightness(&self, age: u8) -> u8 {
+        if age == 0 {
+            return 0;
+        }
+        let steps = (age - 1).min(self.settle_after) as i16;
+        let newborn = self.newborn as i16;
+        let change = self.settled as i16 - newborn;
+        (newborn + change * steps / self.settle_after as i16) as u8
+    }
+}
+
+/// Set every live cell's age to 1 and every other cell's to 0, for a state
+/// that has just been set up
+pub fn start_ages<const W: usize, const H: usize>(state: &Grid<W, H>, ages: &mut Grid<W, H>) {
+    for (age, &cell) in ages.iter_mut().flatten().zip(state.iter().flatten()) {
+        *age = (cell & STATE == 1) as u8;
+    }
+}
+
+/// Bring the ages up to date after a step
+pub fn step_ages<const W: usize, const H: usize>(state: &Grid<W, H>, ages: &mut Grid<W, H>) {
+    for (age, &cell) in ages.iter_mut().flatten().zip(state.iter().flatten()) {
+        *age = if cell & STATE == 1 {
+            age.saturating_add(1)
+        } else {
+            0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{step_state, Rule, Topology};
+
+    #[test]
+    fn ages_count_generations_alive() {
+        // a block next to a blinker
+        let mut state = [[0; 8]; 5];
+        for (r, c) in [(1, 1), (1, 2), (2, 1), (2, 2), (1, 6), (2, 6), (3, 6)] {
+            state[r][c] = 1;
+        }
+        let mut ages = [[0; 8]; 5];
+        start_ages(&state, &mut ages);
+
+        for _ in 0..3 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Plane);
+            step_ages(&state, &mut ages);
+        }
+        assert_eq!(ages[1][1], 4);
+        // the blinker's middle never dies, but its ends keep being reborn
+        assert_eq!(ages[2][6], 4);
+        assert_eq!(ages[2][5], 1);
+        assert_eq!(ages[1][6], 0);
+        assert_eq!(ages[0][0], 0);
+    }
+
+    #[test]
+    fn ages_stop_at_the_top() {
+        let state = [[1; 2]; 1];
+        let mut ages = [[254, 255]];
+        step_ages(&state, &mut ages);
+        assert_eq!(ages, [[255, 255]]);
+    }
+
+    #[test]
+    fn dying_cells_have_no_age() {
+        let mut state = [[0; 4]; 4];
+        state[1][1] = 1;
+        let mut ages = [[0; 4]; 4];
+        start_ages(&state, &mut ages);
+        step_state(&mut state, &Rule::new("B3/S23/3"), &Topology::Plane);
+        step_ages(&state, &mut ages);
+        assert_eq!(state[1][1], 2);
+        assert_eq!(ages[1][1], 0);
+    }
+
+    #[test]
+    fn gradients_settle_at_their_baseline() {
+        let flash = AgeGradient::FLASH;
+        let levels: Vec<u8> = (0..10).map(|age| flash.brightness(age)).collect();
+        assert_eq!(levels, [0, 15, 13, 11, 9, 7, 5, 3, 3, 3]);
+        assert_eq!(flash.brightness(255), 3);
+
+        let grow = AgeGradient::GROW;
+        assert_eq!(grow.brightness(0), 0);
+        assert_eq!(grow.brightness(1), 3);
+        assert_eq!(grow.brightness(7), 9);
+        assert_eq!(grow.brightness(13), 15);
+        assert_eq!(grow.brightness(200), 15);
+
+        for age in 1..=255 {
+            assert_eq!(AgeGradient::FLAT.brightness(age), 15);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Cyclic cellular automata
+//!
+//! Every cell is in one of `n` states arranged in a cycle. A cell moves on to
+//! the next state, wrapping from `n - 1` back to 0, when at least a threshold
+//! number of its neighbors are already in that state. Started from random
+//! noise on the torus, the states settle into waves and spirals.
+//!
+//! Rules are written the way MCell writes them, as in `R1/T3/C3/NM`: the
+//! range, the threshold, the number of states, and the neighborhood shape
+//! (`NM` Moore, `NN` von Neumann, `NC` circular).
+use core::fmt;
+
+use crate::neighborhood::Shape;
+use crate::rng::Rng;
+use crate::rule::MAX_STATES;
+use crate::{labelled, Grid, Palette, Topology, STATE};
+
+/// The biggest supported range
+pub const MAX_RANGE: u8 = 10;
+
+/// A cyclic rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclicRule {
+    /// How far the neighborhood reaches from the middle
+    pub range: u8,
+    /// How many neighbors need to be in the next state for a cell to move on
+    pub threshold: u16,
+    /// How many states there are in the cycle
+    pub states: u8,
+    pub shape: Shape,
+}
+
+/// Reasons a cyclic rule can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CyclicError {
+    /// The string isn't shaped like `R1/T3/C3/NM`
+    Malformed,
+    /// The range was 0 or more than `MAX_RANGE`
+    BadRange,
+    /// The threshold was 0 or more than the size of the neighborhood
+    BadThreshold,
+    /// The number of states was less than 2 or more than `MAX_STATES`
+    BadStates,
+}
+
+impl CyclicRule {
+    /// R1/T3/C3/NM, which grows spirals quickly even on a small grid
+    pub const SPIRALS: CyclicRule = CyclicRule::new("R1/T3/C3/NM");
+    /// R1/T1/C14/NN, Griffeath's original cyclic rule
+    pub const GRIFFEATH: CyclicRule = CyclicRule::new("R1/T1/C14/NN");
+
+    /// Parse a rule, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad rule becomes a compile error.
+    pub const fn new(rule: &str) -> CyclicRule {
+        match CyclicRule::parse(rule) {
+            Ok(rule) => rule,
+            Err(_) => panic!("invalid cyclic rule"),
+        }
+    }
+
+    /// Parse a rule in MCell's notation. The letters may be lowercase and the
+    /// neighborhood may be left off, in which case it is Moore.
+    pub const fn parse(rule: &str) -> Result<CyclicRule, CyclicError> {
+        let bytes = rule.as_bytes();
+
+        let (range, i) = match labelled(bytes, 0, b'R', b'/') {
+            Some(n) => n,
+            None => return Err(CyclicError::Malformed),
+        };
+        let (threshold, i) = match labelled(bytes, i + 1, b'T', b'/') {
+            Some(n) => n,
+            None => return Err(CyclicError::Malformed),
+        };
+        let (states, i) = match labelled(bytes, i + 1, b'C', b'/') {
+            Some(n) => n,
+            None => return Err(CyclicError::Malformed),
+        };
+
+        let mut i = i;
+        let mut shape = Shape::Moore;
+        if i < bytes.len() {
+            if i + 3 != bytes.len() || bytes[i] != b'/' || !bytes[i + 1].eq_ignore_ascii_case(&b'N')
+            {
+                return Err(CyclicError::Malformed);
+            }
+            shape = match bytes[i + 2].to_ascii_uppercase() {
+                b'M' => Shape::Moore,
+                b'N' => Shape::VonNeumann,
+                b'C' => Shape::Circular,
+                _ => return Err(CyclicError::Malformed),
+            };
+            i += 3;
+        }
+        if i != bytes.len() {
+            return Err(CyclicError::Malformed);
+        }
+
+        if range == 0 || range > MAX_RANGE as usize {
+            return Err(CyclicError::BadRange);
+        }
+        if states < 2 || states > MAX_STATES as usize {
+            return Err(CyclicError::BadStates);
+        }
+        // the neighborhood doesn't include the middle cell
+        let size = shape.size(range) - 1;
+        if threshold == 0 || threshold > size {
+            return Err(CyclicError::BadThreshold);
+        }
+
+        Ok(CyclicRule {
+            range: range as u8,
+            threshold: threshold as u16,
+            states: states as u8,
+            shape,
+        })
+    }
+
+    /// The state after `state` in the cycle
+    pub fn successor(&self, state: u8) -> u8 {
+        if state + 1 < self.states {
+            state + 1
+        } else {
+            0
+        }
+    }
+
+    /// Find the state a cell will be in next generation from how many of its
+    /// neighbors are in the state after it
+    pub fn next(&self, state: u8, ahead: u16) -> u8 {
+        if ahead >= self.threshold {
+            self.successor(state)
+        } else {
+            state
+        }
+    }
+}
+
+impl Palette for CyclicRule {
+    /// The states are spread evenly over the display's brightness levels, so
+    /// with 16 states each one gets its own level
+    fn brightness(&self, cell: u8) -> u8 {
+        if cell < self.states {
+            cell * 15 / (self.states - 1)
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for CyclicRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shape = match self.shape {
+            Shape::Moore => 'M',
+            Shape::VonNeumann => 'N',
+            Shape::Circular => 'C',
+        };
+        write!(
+            f,
+            "R{}/T{}/C{}/N{}",
+            self.range, self.threshold, self.states, shape
+        )
+    }
+}
+
+/// Fill the grid with random states
+pub fn scatter<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &CyclicRule,
+    rng: &mut Rng,
+) {
+    for cell in state.iter_mut().flatten() {
+        *cell = rng.below(rule.states as u32) as u8;
+    }
+}
+
+/// Advance the simulation by one generation
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &CyclicRule,
+    topology: &Topology,
+) {
+    let range = rule.range as isize;
+    for row in 0..H {
+        for col in 0..W {
+            let cell = state[row][col] & STATE;
+            let successor = rule.successor(cell);
+
+            let mut ahead = 0;
+            for dr in -range..=range {
+                let half_width = rule
+                    .shape
+                    .half_width(rule.range as usize, dr.unsigned_abs())
+                    as isize;
+                for dc in -half_width..=half_width {
+                    if dr == 0 && dc == 0 {
+                        continue;
+                    }
+                    let r = row as isize + dr;
+                    let c = col as isize + dc;
+                    if let Some((r, c)) = topology.locate(r, c, W, H) {
+                        ahead += (state[r][c] & STATE == successor) as u16;
+                    }
+                }
+            }
+
+            state[row][col] |= rule.next(cell, ahead) << 4;
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Twist;
+
+    #[test]
+    fn parses_mcell_notation() {
+        assert_eq!(
+            CyclicRule::parse("R1/T3/C3/NM"),
+            Ok(CyclicRule {
+                range: 1,
+                threshold: 3,
+                states: 3,
+                shape: Shape::Moore,
+            })
+        );
+        assert_eq!(CyclicRule::parse("r1/t3/c3"), Ok(CyclicRule::SPIRALS));
+        assert_eq!(
+            CyclicRule::parse("R2/T5/C16/NC").map(|r| (r.states, r.shape)),
+            Ok((16, Shape::Circular))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert_eq!(CyclicRule::parse(""), Err(CyclicError::Malformed));
+        assert_eq!(CyclicRule::parse("R1/T3"), Err(CyclicError::Malformed));
+        assert_eq!(CyclicRule::parse("R1,T3,C3"), Err(CyclicError::Malformed));
+        assert_eq!(
+            CyclicRule::parse("R1/T3/C3/NX"),
+            Err(CyclicError::Malformed)
+        );
+        assert_eq!(CyclicRule::parse("R0/T1/C3"), Err(CyclicError::BadRange));
+        assert_eq!(CyclicRule::parse("R11/T1/C3"), Err(CyclicError::BadRange));
+        assert_eq!(CyclicRule::parse("R1/T1/C1"), Err(CyclicError::BadStates));
+        assert_eq!(CyclicRule::parse("R1/T1/C17"), Err(CyclicError::BadStates));
+        assert_eq!(
+            CyclicRule::parse("R1/T0/C3"),
+            Err(CyclicError::BadThreshold)
+        );
+        assert_eq!(
+            CyclicRule::parse("R1/T9/C3"),
+            Err(CyclicError::BadThreshold)
+        );
+        assert_eq!(
+            CyclicRule::parse("R1/T5/C3/NN"),
+            Err(CyclicError::BadThreshold)
+        );
+        assert!(CyclicRule::parse("R1/T8/C3").is_ok());
+    }
+
+    #[test]
+    fn displays_in_mcell_notation() {
+        assert_eq!(format!("{}", CyclicRule::SPIRALS), "R1/T3/C3/NM");
+        assert_eq!(format!("{}", CyclicRule::GRIFFEATH), "R1/T1/C14/NN");
+    }
+
+    #[test]
+    fn cells_move_on_when_enough_neighbors_are_ahead() {
+        let rule = CyclicRule::SPIRALS;
+        let mut state = [[0; 16]; 8];
+        state[3][4] = 1;
+        state[3][5] = 1;
+        state[4][4] = 1;
+        step_state(&mut state, &rule, &Topology::Torus);
+        // only the cells touching all three ones move on
+        assert_eq!(state[4][5], 1);
+        assert_eq!(state[2][4], 0);
+        assert_eq!(crate::population(&state), 4);
+
+        // and the last state wraps round to the first
+        let mut state = [[2; 16]; 8];
+        state[0][0] = 0;
+        state[0][1] = 0;
+        state[1][0] = 0;
+        step_state(&mut state, &rule, &Topology::Torus);
+        assert_eq!(state[1][1], 0);
+        assert_eq!(state[7][15], 2);
+    }
+
+    #[test]
+    fn every_state_gets_its_own_brightness() {
+        let rule = CyclicRule::new("R1/T1/C16/NN");
+        for state in 0..16 {
+            assert_eq!(rule.brightness(state), state);
+        }
+        let rule = CyclicRule::SPIRALS;
+        assert_eq!([0, 1, 2, 3].map(|s| rule.brightness(s)), [0, 7, 15, 0]);
+    }
+
+    #[test]
+    fn long_ranges_wrap_round_twisted_topologies() {
+        // a neighborhood 21 cells across, wider than the panel is tall
+        let rule = CyclicRule::new("R10/T140/C3/NM");
+        let mut rng = Rng::new(9);
+
+        // a Klein bottle steps the same as the torus made of it and a
+        // mirrored copy
+        let mut state = [[0; 16]; 8];
+        scatter(&mut state, &rule, &mut rng);
+        let mut cover = [[0; 16]; 16];
+        for row in 0..8 {
+            for col in 0..16 {
+                cover[row][col] = state[row][col];
+                cover[row + 8][15 - col] = state[row][col];
+            }
+        }
+        let before = state;
+        step_state(&mut state, &rule, &Topology::Klein(Twist::Horizontal));
+        step_state(&mut cover, &rule, &Topology::Torus);
+        assert_ne!(state, before);
+        assert_eq!(state[..], cover[..8]);
+
+        // turning the cross-surface half way round and transposing the
+        // sphere move every cell's neighbors along with it
+        let mut state = [[0; 16]; 8];
+        scatter(&mut state, &rule, &mut rng);
+        let mut turned = state;
+        for row in 0..8 {
+            for col in 0..16 {
+                turned[7 - row][15 - col] = state[row][col];
+            }
+        }
+        step_state(&mut state, &rule, &Topology::CrossSurface);
+        step_state(&mut turned, &rule, &Topology::CrossSurface);
+        for row in 0..8 {
+            for col in 0..16 {
+                assert_eq!(turned[7 - row][15 - col], state[row][col]);
+            }
+        }
+
+        let mut state = [[0; 8]; 8];
+        scatter(&mut state, &rule, &mut rng);
+        let mut transposed = state;
+        for row in 0..8 {
+            for col in 0..8 {
+                transposed[col][row] = state[row][col];
+            }
+        }
+        step_state(&mut state, &rule, &Topology::Sphere);
+        step_state(&mut transposed, &rule, &Topology::Sphere);
+        for row in 0..8 {
+            for col in 0..8 {
+                assert_eq!(transposed[col][row], state[row][col]);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_organizes_itself_on_the_torus() {
+        // the panel is too small for spirals to last, so this uses a bigger
+        // torus
+        let rule = CyclicRule::SPIRALS;
+        let mut state = [[0; 48]; 48];
+        scatter(&mut state, &rule, &mut Rng::new(5));
+
+        for _ in 0..300 {
+            let before = state;
+            step_state(&mut state, &rule, &Topology::Torus);
+            // cells only ever stay put or step forward
+            for (&a, &b) in before.iter().flatten().zip(state.iter().flatten()) {
+                assert!(b == a || b == rule.successor(a));
+            }
+        }
+
+        // once the waves have taken over, they keep sweeping across most of
+        // the grid rather than freezing into blocks
+        let mut moved = [[false; 48]; 48];
+        for _ in 0..rule.states * 2 {
+            let before = state;
+            step_state(&mut state, &rule, &Topology::Torus);
+            for row in 0..48 {
+                for col in 0..48 {
+                    moved[row][col] |= before[row][col] != state[row][col];
+                }
+            }
+        }
+        let stuck = moved.iter().flatten().filter(|&&m| !m).count();
+        assert!(stuck < 48 * 48 / 2, "{} cells stuck", stuck);
+    }
+}
@@ -0,0 +1,214 @@
+//! Wolfram's elementary cellular automata, shown as a scrolling history
+//!
+//! An elementary automaton is a single row of cells. Each cell's next state
+//! depends on itself and the cells either side of it, and the rule number's
+//! bits say what happens for each of the eight combinations: bit `4l + 2c + r`
+//! is the next state of a cell `c` with neighbors `l` and `r`.
+//!
+//! The grid holds the history, with the newest generation on the bottom row
+//! and older ones scrolling up and off the top.
+use crate::rng::Rng;
+use crate::{Grid, Palette, STATE};
+
+/// What is past the ends of the row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// The ends are joined, so the row is a ring
+    Wrap,
+    /// Everything past the ends is dead
+    Fixed,
+}
+
+/// How the first generation is filled in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seed {
+    /// One live cell in the given column
+    Single(usize),
+    /// Each cell alive with even odds
+    Random,
+}
+
+/// An elementary rule and the boundary it runs with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elementary {
+    pub rule: u8,
+    pub boundary: Boundary,
+}
+
+impl Elementary {
+    pub const fn new(rule: u8, boundary: Boundary) -> Elementary {
+        Elementary { rule, boundary }
+    }
+
+    /// Find a cell's next state from it and its neighbors
+    pub fn next(&self, left: u8, center: u8, right: u8) -> u8 {
+        (self.rule >> (left << 2 | center << 1 | right)) & 1
+    }
+
+    /// Compute the generation after `row`
+    pub fn next_row<const W: usize>(&self, row: &[u8; W]) -> [u8; W] {
+        let edge = |col: usize| match self.boundary {
+            Boundary::Wrap => row[col] & STATE,
+            Boundary::Fixed => 0,
+        };
+
+        let mut next = [0; W];
+        for col in 0..W {
+            let left = if col > 0 {
+                row[col - 1] & STATE
+            } else {
+                edge(W - 1)
+            };
+            let right = if col + 1 < W {
+                row[col + 1] & STATE
+            } else {
+                edge(0)
+            };
+            next[col] = self.next(left, row[col] & STATE, right);
+        }
+        next
+    }
+}
+
+impl Palette for Elementary {
+    fn brightness(&self, cell: u8) -> u8 {
+        if cell == 1 {
+            15
+        } else {
+            0
+        }
+    }
+}
+
+/// Clear the history and put a first generation on the bottom row
+///
+/// A single cell in a column off the grid is skipped, leaving the row empty.
+pub fn start<const W: usize, const H: usize>(history: &mut Grid<W, H>, seed: Seed, rng: &mut Rng) {
+    *history = [[0; W]; H];
+    let row = &mut history[H - 1];
+    match seed {
+        Seed::Single(col) => {
+            if let Some(cell) = row.get_mut(col) {
+                *cell = 1;
+            }
+        }
+        Seed::Random => {
+            for cell in row.iter_mut() {
+                *cell = rng.chance(1, 2) as u8;
+            }
+        }
+    }
+}
+
+/// Push the next generation in at the bottom, scrolling the older ones up
+pub fn step_history<const W: usize, const H: usize>(history: &mut Grid<W, H>, rule: &Elementary) {
+    let next = rule.next_row(&history[H - 1]);
+    history.copy_within(1.., 0);
+    history[H - 1] = next;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row<const W: usize>(text: &str) -> [u8; W] {
+        let mut row = [0; W];
+        for (cell, c) in row.iter_mut().zip(text.chars()) {
+            *cell = (c == 'o') as u8;
+        }
+        row
+    }
+
+    #[test]
+    fn rule_bits_pick_the_next_state() {
+        let rule_30 = Elementary::new(30, Boundary::Fixed);
+        // 30 is 0b00011110
+        let expected = [0, 1, 1, 1, 1, 0, 0, 0];
+        for (pattern, &next) in expected.iter().enumerate() {
+            let pattern = pattern as u8;
+            assert_eq!(
+                rule_30.next(pattern >> 2, (pattern >> 1) & 1, pattern & 1),
+                next
+            );
+        }
+    }
+
+    #[test]
+    fn rule_30_grows_from_one_cell() {
+        let rule = Elementary::new(30, Boundary::Fixed);
+        let mut history = [[0; 16]; 8];
+        start(&mut history, Seed::Single(7), &mut Rng::new(1));
+        for _ in 0..7 {
+            step_history(&mut history, &rule);
+        }
+        let expected = [
+            ".......o........",
+            "......ooo.......",
+            ".....oo..o......",
+            "....oo.oooo.....",
+            "...oo..o...o....",
+            "..oo.oooo.ooo...",
+            ".oo..o....o..o..",
+            "oo.oooo..oooooo.",
+        ];
+        for (line, text) in history.iter().zip(expected.iter()) {
+            assert_eq!(*line, row::<16>(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn boundaries_decide_what_is_past_the_ends() {
+        // rule 2 moves every isolated live cell one to the left
+        let wrap = Elementary::new(2, Boundary::Wrap);
+        let fixed = Elementary::new(2, Boundary::Fixed);
+        let start: [u8; 8] = row("o.......");
+        assert_eq!(wrap.next_row(&start), row(".......o"));
+        assert_eq!(fixed.next_row(&start), [0; 8]);
+
+        // rule 1 turns on cells whose whole neighborhood is dead
+        let edges: [u8; 4] = row("...o");
+        assert_eq!(
+            Elementary::new(1, Boundary::Fixed).next_row(&edges),
+            row("oo..")
+        );
+        assert_eq!(
+            Elementary::new(1, Boundary::Wrap).next_row(&edges),
+            row(".o..")
+        );
+    }
+
+    #[test]
+    fn rule_110_runs_on_a_ring() {
+        // rule 110 with a single cell on the right grows to the left
+        let rule = Elementary::new(110, Boundary::Wrap);
+        let mut history = [[0; 16]; 8];
+        start(&mut history, Seed::Single(15), &mut Rng::new(1));
+        step_history(&mut history, &rule);
+        step_history(&mut history, &rule);
+        assert_eq!(history[7], row::<16>(".............ooo"));
+        assert_eq!(history[6], row::<16>("..............oo"));
+        assert_eq!(history[5], row::<16>("...............o"));
+        assert_eq!(history[4], [0; 16]);
+    }
+
+    #[test]
+    fn random_seeds_are_repeatable() {
+        let mut a = [[0; 16]; 8];
+        let mut b = [[1; 16]; 8];
+        start(&mut a, Seed::Random, &mut Rng::new(9));
+        start(&mut b, Seed::Random, &mut Rng::new(9));
+        assert_eq!(a, b);
+        assert_eq!(a[..7], [[0; 16]; 7]);
+        let live = a[7].iter().filter(|&&c| c == 1).count();
+        assert!(0 < live && live < 16);
+    }
+
+    #[test]
+    fn single_cells_off_the_grid_are_skipped() {
+        let mut history = [[1; 16]; 8];
+        start(&mut history, Seed::Single(16), &mut Rng::new(1));
+        assert_eq!(history, [[0; 16]; 8]);
+        start(&mut history, Seed::Single(usize::MAX), &mut Rng::new(1));
+        assert_eq!(history, [[0; 16]; 8]);
+    }
+}
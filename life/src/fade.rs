@@ -0,0 +1,168 @@
+//! Cross-fades between display images
+//!
+//! Instead of snapping straight to each new generation, a `Fade` moves every
+//! pixel's brightness a step at a time from the image on the display to the
+//! new one, one step per scan. Births fade in and deaths fade out.
+//!
+//! Starting a new fade before the last one finishes carries on from
+//! wherever the last one got to, so nothing jumps.
+use crate::Grid;
+
+/// A fade from one image to another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fade<const W: usize, const H: usize> {
+    from: Grid<W, H>,
+    to: Grid<W, H>,
+    /// How many scans a fade takes
+    pub frames: u8,
+    /// How many scans into the fade we are
+    frame: u8,
+}
+
+impl<const W: usize, const H: usize> Fade<W, H> {
+    /// A fade that takes `frames` scans, starting from a dark display.
+    /// Panics if `frames` is 0.
+    pub const fn new(frames: u8) -> Fade<W, H> {
+        if frames == 0 {
+            panic!("a fade needs at least one frame");
+        }
+        Fade {
+            from: [[0; W]; H],
+            to: [[0; W]; H],
+            frames,
+            frame: frames,
+        }
+    }
+
+    /// Start fading towards a new image
+    pub fn start(&mut self, image: &Grid<W, H>) {
+        self.from = self.current();
+        self.to = *image;
+        self.frame = 0;
+    }
+
+    /// Whether the display has reached the image it was fading to
+    pub fn is_done(&self) -> bool {
+        self.frame == self.frames
+    }
+
+    /// The image partway through the fade
+    pub fn current(&self) -> Grid<W, H> {
+        let mut image = self.to;
+        if self.is_done() {
+            return image;
+        }
+        for (pixel, &from) in image.iter_mut().flatten().zip(self.from.iter().flatten()) {
+            // a whole byte of change times a whole byte of frames doesn't fit
+            // in an i16
+            let change = *pixel as i32 - from as i32;
+            // round to the nearest level, so short fades still move on every
+            // scan
+            let moved = (2 * change * self.frame as i32 + self.frames as i32 * change.signum())
+                / (2 * self.frames as i32);
+            *pixel = (from as i32 + moved) as u8;
+        }
+        image
+    }
+
+    /// Move the fade on by a scan and write the image for that scan
+    ///
+    /// This runs on every scan, so once the fade is over it only copies the
+    /// image rather than working anything out.
+    pub fn show(&mut self, image: &mut Grid<W, H>) {
+        if self.is_done() {
+            *image = self.to;
+            return;
+        }
+        self.frame += 1;
+        *image = self.current();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn births_fade_in_and_deaths_fade_out() {
+        let mut fade = Fade::<2, 1>::new(4);
+        let mut image = [[0; 2]; 1];
+        fade.start(&[[15, 0]]);
+        fade.show(&mut image);
+
+        fade.start(&[[0, 15]]);
+        let mut frames = Vec::new();
+        while !fade.is_done() {
+            fade.show(&mut image);
+            frames.push(image[0]);
+        }
+        // the first cell started at 4, a quarter of the way in
+        assert_eq!(frames, [[3, 4], [2, 8], [1, 11], [0, 15]]);
+
+        // once the fade is over, the image stays put
+        fade.show(&mut image);
+        assert_eq!(image, [[0, 15]]);
+    }
+
+    #[test]
+    fn one_frame_snaps_straight_to_the_image() {
+        let mut fade = Fade::<3, 1>::new(1);
+        let mut image = [[0; 3]; 1];
+        fade.start(&[[15, 7, 1]]);
+        assert!(!fade.is_done());
+        fade.show(&mut image);
+        assert_eq!(image, [[15, 7, 1]]);
+        assert!(fade.is_done());
+    }
+
+    #[test]
+    fn restarting_carries_on_from_partway() {
+        let mut fade = Fade::<1, 1>::new(8);
+        let mut image = [[0; 1]; 1];
+        fade.start(&[[15]]);
+        for _ in 0..4 {
+            fade.show(&mut image);
+        }
+        assert_eq!(image, [[8]]);
+
+        fade.start(&[[0]]);
+        assert_eq!(fade.current(), [[8]]);
+        let mut last = 8;
+        while !fade.is_done() {
+            fade.show(&mut image);
+            assert!(image[0][0] <= last);
+            last = image[0][0];
+        }
+        assert_eq!(image, [[0]]);
+    }
+
+    #[test]
+    fn every_step_of_a_long_fade_moves_or_holds() {
+        let mut fade = Fade::<1, 1>::new(30);
+        let mut image = [[0; 1]; 1];
+        fade.start(&[[15]]);
+        let mut last = 0;
+        for _ in 0..30 {
+            fade.show(&mut image);
+            assert!(image[0][0] >= last && image[0][0] - last <= 1);
+            last = image[0][0];
+        }
+        assert_eq!(last, 15);
+    }
+
+    #[test]
+    fn whole_bytes_fade_without_overflowing() {
+        let mut fade = Fade::<2, 1>::new(255);
+        let mut image = [[0; 2]; 1];
+        fade.start(&[[255, 0]]);
+        fade.show(&mut image);
+        fade.start(&[[0, 255]]);
+        let mut last = image[0];
+        while !fade.is_done() {
+            fade.show(&mut image);
+            assert!(image[0][0] <= last[0] && image[0][1] >= last[1]);
+            last = image[0];
+        }
+        assert_eq!(image, [[0, 255]]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Isotropic non-totalistic rules, written in Hensel notation
+//!
+//! Rather than only counting live neighbors, these rules look at how the live
+//! neighbors are arranged. Each count is split into the arrangements that are
+//! the same up to rotation and reflection, and each of those gets a letter, so
+//! `B2-a/S12` is born on any two neighbors except two that are touching, and
+//! tlife `B3/S2-i34q` survives on two neighbors unless they are on opposite
+//! sides.
+//!
+//! | Count | Letters |
+//! |---|---|
+//! | 1, 7 | `ce` |
+//! | 2, 6 | `ceaikn` |
+//! | 3, 5 | `ceaiknjqry` |
+//! | 4 | `ceaiknjqrytwz` |
+//!
+//! The letters for more than four neighbors name the arrangement of the dead
+//! neighbors, so `5c` is the opposite of `3c`.
+//!
+//! The eight neighbors of a cell are packed into a byte in the same order as
+//! `Neighborhood::Moore`'s offsets, with the top left neighbor in bit 0:
+//!
+//! ```text
+//! 0 1 2
+//! 3 . 4
+//! 5 6 7
+//! ```
+
+/// A set of neighbor configurations, with one bit for each of the 256 ways
+/// the eight neighbors of a cell can be alive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Configurations(pub [u32; 8]);
+
+/// The birth and survival conditions of a non-totalistic rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Isotropic {
+    /// Configurations that bring a dead cell to life
+    pub birth: Configurations,
+    /// Configurations that keep a live cell alive
+    pub survival: Configurations,
+}
+
+/// One configuration for each letter, in Golly's order. The letters for
+/// more than four neighbors are the complements of these.
+const LETTERS: [&[(u8, u8)]; 5] = [
+    &[],
+    &[(b'c', 0b00000001), (b'e', 0b00000010)],
+    &[
+        (b'c', 0b00000101),
+        (b'e', 0b00001010),
+        (b'a', 0b00000011),
+        (b'i', 0b00011000),
+        (b'k', 0b00010001),
+        (b'n', 0b00100100),
+    ],
+    &[
+        (b'c', 0b00100101),
+        (b'e', 0b00011010),
+        (b'a', 0b00001011),
+        (b'i', 0b00000111),
+        (b'k', 0b00110010),
+        (b'n', 0b00001101),
+        (b'j', 0b00001110),
+        (b'q', 0b00100110),
+        (b'r', 0b00011001),
+        (b'y', 0b00110001),
+    ],
+    &[
+        (b'c', 0b10100101),
+        (b'e', 0b01011010),
+        (b'a', 0b00001111),
+        (b'i', 0b00011101),
+        (b'k', 0b00110011),
+        (b'n', 0b00100111),
+        (b'j', 0b00111010),
+        (b'q', 0b00110110),
+        (b'r', 0b00011011),
+        (b'y', 0b00110101),
+        (b't', 0b00111001),
+        (b'w', 0b00101110),
+        (b'z', 0b00111100),
+    ],
+];
+
+/// Where each neighbor ends up after a quarter turn clockwise
+const ROTATE: [u8; 8] = [2, 4, 7, 1, 6, 0, 3, 5];
+
+/// Where each neighbor ends up after flipping left to right
+const MIRROR: [u8; 8] = [2, 1, 0, 4, 3, 7, 6, 5];
+
+impl Configurations {
+    pub const EMPTY: Configurations = Configurations([0; 8]);
+
+    pub const fn contains(&self, configuration: u8) -> bool {
+        self.0[(configuration >> 5) as usize] & (1 << (configuration & 31)) != 0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        let mut i = 0;
+        while i < 8 {
+            if self.0[i] != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    pub(crate) const fn union(self, other: Configurations) -> Configurations {
+        let mut words = self.0;
+        let mut i = 0;
+        while i < 8 {
+            words[i] |= other.0[i];
+            i += 1;
+        }
+        Configurations(words)
+    }
+
+    pub(crate) const fn intersection(self, other: Configurations) -> Configurations {
+        let mut words = self.0;
+        let mut i = 0;
+        while i < 8 {
+            words[i] &= other.0[i];
+            i += 1;
+        }
+        Configurations(words)
+    }
+
+    pub(crate) const fn difference(self, other: Configurations) -> Configurations {
+        let mut words = self.0;
+        let mut i = 0;
+        while i < 8 {
+            words[i] &= !other.0[i];
+            i += 1;
+        }
+        Configurations(words)
+    }
+
+    /// Every configuration with `count` live neighbors
+    pub const fn with_count(count: u8) -> Configurations {
+        let mut words = [0; 8];
+        let mut configuration = 0;
+        while configuration < 256 {
+            if (configuration as u8).count_ones() == count as u32 {
+                words[configuration >> 5] |= 1 << (configuration & 31);
+            }
+            configuration += 1;
+        }
+        Configurations(words)
+    }
+
+    /// The configurations a Hensel letter stands for, or `None` if the letter
+    /// isn't used with that many neighbors
+    pub const fn letter(count: u8, letter: u8) -> Option<Configurations> {
+        if count > 8 {
+            return None;
+        }
+        let flipped = count > 4;
+        let letters = LETTERS[if flipped { 8 - count } else { count } as usize];
+
+        let mut i = 0;
+        while i < letters.len() {
+            let (name, configuration) = letters[i];
+            if name == letter {
+                let configuration = if flipped {
+                    !configuration
+                } else {
+                    configuration
+                };
+                return Some(Configurations::symmetries(configuration));
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// A configuration along with all its rotations and reflections
+    const fn symmetries(configuration: u8) -> Configurations {
+        let mut words = [0; 8];
+        let mut turned = configuration;
+        let mut turns = 0;
+        while turns < 4 {
+            let mirrored = permute(turned, &MIRROR);
+            words[(turned >> 5) as usize] |= 1 << (turned & 31);
+            words[(mirrored >> 5) as usize] |= 1 << (mirrored & 31);
+            turned = permute(turned, &ROTATE);
+            turns += 1;
+        }
+        Configurations(words)
+    }
+}
+
+/// The letters that can follow a count, in Golly's order
+pub(crate) fn letters(count: u8) -> impl Iterator<Item = u8> {
+    let count = if count > 4 { 8 - count } else { count };
+    LETTERS[count as usize].iter().map(|&(letter, _)| letter)
+}
+
+/// Move each neighbor to the position `to` says
+const fn permute(configuration: u8, to: &[u8; 8]) -> u8 {
+    let mut moved = 0;
+    let mut bit = 0;
+    while bit < 8 {
+        if configuration & (1 << bit) != 0 {
+            moved |= 1 << to[bit];
+        }
+        bit += 1;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_split_each_count() {
+        for count in 0..=8 {
+            let mut covered = Configurations::EMPTY;
+            for letter in letters(count) {
+                let class = Configurations::letter(count, letter).unwrap();
+                assert!(
+                    covered.intersection(class).is_empty(),
+                    "{}{} overlaps",
+                    count,
+                    letter as char
+                );
+                covered = covered.union(class);
+            }
+            if count == 0 || count == 8 {
+                assert!(covered.is_empty());
+            } else {
+                assert_eq!(covered, Configurations::with_count(count), "{}", count);
+            }
+        }
+    }
+
+    #[test]
+    fn letters_have_the_expected_shapes() {
+        // 2a is two touching neighbors, wherever they are
+        let adjacent = Configurations::letter(2, b'a').unwrap();
+        assert!(adjacent.contains(0b00000011));
+        assert!(adjacent.contains(0b11000000));
+        assert!(adjacent.contains(0b00010100));
+        assert!(!adjacent.contains(0b00000101));
+
+        // 2i is opposite edges, 2n opposite corners
+        let opposite = Configurations::letter(2, b'i').unwrap();
+        assert!(opposite.contains(0b01000010));
+        assert!(!opposite.contains(0b10000001));
+        assert!(Configurations::letter(2, b'n')
+            .unwrap()
+            .contains(0b10000001));
+
+        // 4c and 4e only have one configuration each
+        assert_eq!(
+            Configurations::letter(4, b'c'),
+            Some(Configurations::symmetries(0b10100101))
+        );
+        assert!(Configurations::letter(4, b'e')
+            .unwrap()
+            .contains(0b01011010));
+
+        // 7c is everything but one corner
+        assert!(Configurations::letter(7, b'c')
+            .unwrap()
+            .contains(0b11111110));
+        assert!(Configurations::letter(7, b'e')
+            .unwrap()
+            .contains(0b11111101));
+    }
+
+    #[test]
+    fn rejects_letters_for_the_wrong_count() {
+        assert_eq!(Configurations::letter(0, b'c'), None);
+        assert_eq!(Configurations::letter(8, b'c'), None);
+        assert_eq!(Configurations::letter(1, b'a'), None);
+        assert_eq!(Configurations::letter(3, b't'), None);
+        assert_eq!(Configurations::letter(4, b'x'), None);
+    }
+}
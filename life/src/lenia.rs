@@ -0,0 +1,478 @@
+//! Lenia, a cellular automaton with continuous states
+//!
+//! Every cell holds a value from 0 to 1. Each generation, a cell adds up its
+//! neighbors within `radius` cells, weighted by a ring-shaped kernel, and the
+//! total decides through a growth function whether the cell grows or shrinks.
+//! Cells grow most when the total is near `mu`, and `sigma` sets how near it
+//! has to be. `dt` is how much of the growth happens each generation.
+//!
+//! The M0 has no floating point unit, so everything is done in Q16.16 fixed
+//! point, and the kernel and growth function are Lenia's polynomial ones,
+//! which need nothing more than multiplication:
+//!
+//! ```text
+//! kernel(r) = (4r(1 - r))^4, for r from 0 to 1 across the radius
+//! growth(u) = 2(1 - (u - mu)^2 / 9sigma^2)^4 - 1, or -1 if that's negative
+//! ```
+//!
+//! The grid always wraps round like a torus. Values are kept in a `Field`,
+//! which `quantize` turns into brightness levels for `show_state`.
+use crate::rng::Rng;
+use crate::{Grid, Palette};
+
+/// A Q16.16 fixed point number
+pub type Fixed = i32;
+
+/// 1 as a `Fixed`
+pub const ONE: Fixed = 1 << 16;
+
+/// The smallest supported radius. With a radius of 1 the ring misses every
+/// neighbor.
+pub const MIN_RADIUS: u8 = 2;
+
+/// The biggest supported radius
+pub const MAX_RADIUS: u8 = 6;
+
+/// How wide the kernel table is
+const KERNEL_SIZE: usize = 2 * MAX_RADIUS as usize + 1;
+
+/// Cell values, from 0 to `ONE`, laid out like a `Grid`
+pub type Field<const W: usize, const H: usize> = [[Fixed; W]; H];
+
+/// The fraction `numerator / denominator` as a `Fixed`
+pub const fn fixed(numerator: i32, denominator: i32) -> Fixed {
+    (((numerator as i64) << 16) / denominator as i64) as Fixed
+}
+
+/// Multiply two `Fixed`s
+const fn mul(a: Fixed, b: Fixed) -> Fixed {
+    ((a as i64 * b as i64) >> 16) as Fixed
+}
+
+/// Lenia's parameters, along with the kernel they make
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lenia {
+    /// How far the kernel reaches from the middle
+    pub radius: u8,
+    /// The weighted total that cells grow best at
+    pub mu: Fixed,
+    /// How far from `mu` the total can be for cells to still grow
+    pub sigma: Fixed,
+    /// How much of the growth happens each generation
+    pub dt: Fixed,
+    /// The weight of each cell within the radius, adding up to `ONE`
+    kernel: [[Fixed; KERNEL_SIZE]; KERNEL_SIZE],
+}
+
+/// Reasons Lenia's parameters can be invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeniaError {
+    /// The radius was less than `MIN_RADIUS` or more than `MAX_RADIUS`
+    BadRadius,
+    /// Mu was less than 0 or more than 1
+    BadMu,
+    /// Sigma wasn't more than 0 and at most 1
+    BadSigma,
+    /// The time step wasn't more than 0 and at most 1
+    BadTimeStep,
+}
+
+impl Lenia {
+    /// Parameters that keep moving on a torus as small as the panel
+    pub const PANEL: Lenia = Lenia::new(4, fixed(15, 100), fixed(2, 100), fixed(1, 10));
+
+    /// Set up Lenia, panicking if the parameters are invalid
+    ///
+    /// This is meant for consts, where bad parameters become a compile
+    /// error.
+    pub const fn new(radius: u8, mu: Fixed, sigma: Fixed, dt: Fixed) -> Lenia {
+        match Lenia::try_new(radius, mu, sigma, dt) {
+            Ok(lenia) => lenia,
+            Err(_) => panic!("invalid Lenia parameters"),
+        }
+    }
+
+    /// Set up Lenia, working out the kernel for the radius
+    pub const fn try_new(
+        radius: u8,
+        mu: Fixed,
+        sigma: Fixed,
+        dt: Fixed,
+    ) -> Result<Lenia, LeniaError> {
+        if radius < MIN_RADIUS || radius > MAX_RADIUS {
+            return Err(LeniaError::BadRadius);
+        }
+        if mu < 0 || mu > ONE {
+            return Err(LeniaError::BadMu);
+        }
+        if sigma <= 0 || sigma > ONE {
+            return Err(LeniaError::BadSigma);
+        }
+        if dt <= 0 || dt > ONE {
+            return Err(LeniaError::BadTimeStep);
+        }
+
+        let mut kernel = [[0; KERNEL_SIZE]; KERNEL_SIZE];
+        let mut total: i64 = 0;
+        let reach = radius as i64;
+        let mut dr = -reach;
+        while dr <= reach {
+            let mut dc = -reach;
+            while dc <= reach {
+                // the distance as a fraction of the radius
+                let r = (isqrt(((dr * dr + dc * dc) as u64) << 32) as i64 / reach) as Fixed;
+                let weight = if r < ONE {
+                    let ring = 4 * mul(r, ONE - r);
+                    let squared = mul(ring, ring);
+                    mul(squared, squared)
+                } else {
+                    0
+                };
+                kernel[(dr + MAX_RADIUS as i64) as usize][(dc + MAX_RADIUS as i64) as usize] =
+                    weight;
+                total += weight as i64;
+                dc += 1;
+            }
+            dr += 1;
+        }
+
+        // scale the weights so they add up to one, and the weighted total
+        // stays between 0 and 1
+        let mut row = 0;
+        while row < KERNEL_SIZE {
+            let mut col = 0;
+            while col < KERNEL_SIZE {
+                kernel[row][col] =
+                    ((kernel[row][col] as i64 * ONE as i64 + total / 2) / total) as Fixed;
+                col += 1;
+            }
+            row += 1;
+        }
+
+        Ok(Lenia {
+            radius,
+            mu,
+            sigma,
+            dt,
+            kernel,
+        })
+    }
+
+    /// The kernel's weight for a neighbor `dr` rows and `dc` columns away
+    pub fn weight(&self, dr: isize, dc: isize) -> Fixed {
+        let reach = MAX_RADIUS as isize;
+        if dr.abs() > reach || dc.abs() > reach {
+            0
+        } else {
+            self.kernel[(dr + reach) as usize][(dc + reach) as usize]
+        }
+    }
+
+    /// How much a cell grows, from -1 to 1, given its weighted total
+    pub fn growth(&self, potential: Fixed) -> Fixed {
+        // anything over 3 sigma away has no growth, so clamping keeps the
+        // square from overflowing without changing the answer
+        let reach = 3 * self.sigma as i64 + 1;
+        let distance = (potential as i64 - self.mu as i64).clamp(-reach, reach);
+        let sigma = self.sigma as i64;
+        // both sides are Q32.32, so the quotient comes out in Q16.16
+        let fall = ((distance * distance) << 16) / (9 * sigma * sigma);
+        if fall >= ONE as i64 {
+            return -ONE;
+        }
+        let closeness = ONE - fall as Fixed;
+        let squared = mul(closeness, closeness);
+        2 * mul(squared, squared) - ONE
+    }
+}
+
+impl Palette for Lenia {
+    /// Quantized fields are already brightness levels
+    fn brightness(&self, cell: u8) -> u8 {
+        cell.min(15)
+    }
+}
+
+/// The integer square root of `n`, rounded down
+const fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method, starting at or above the root so it comes down to it
+    let mut x = n;
+    let mut y = n / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Give about half the cells random values
+pub fn scatter<const W: usize, const H: usize>(field: &mut Field<W, H>, rng: &mut Rng) {
+    for cell in field.iter_mut().flatten() {
+        *cell = if rng.chance(1, 2) {
+            rng.below(ONE as u32 + 1) as Fixed
+        } else {
+            0
+        };
+    }
+}
+
+/// Advance the simulation by one generation
+pub fn step_field<const W: usize, const H: usize>(field: &mut Field<W, H>, lenia: &Lenia) {
+    // every cell needs the old values of its neighbors, and the values don't
+    // fit in a nibble, so work from a copy
+    let old = *field;
+    let reach = lenia.radius as isize;
+    for row in 0..H {
+        for col in 0..W {
+            let mut potential: i64 = 0;
+            for dr in -reach..=reach {
+                let r = (row as isize + dr).rem_euclid(H as isize) as usize;
+                for dc in -reach..=reach {
+                    let c = (col as isize + dc).rem_euclid(W as isize) as usize;
+                    potential += lenia.weight(dr, dc) as i64 * old[r][c] as i64;
+                }
+            }
+            let potential = (potential >> 16) as Fixed;
+
+            let grown = old[row][col] + mul(lenia.dt, lenia.growth(potential));
+            field[row][col] = grown.clamp(0, ONE);
+        }
+    }
+}
+
+/// Turn every value into one of the display's 16 brightness levels
+pub fn quantize<const W: usize, const H: usize>(field: &Field<W, H>, state: &mut Grid<W, H>) {
+    for (state_row, field_row) in state.iter_mut().zip(field.iter()) {
+        for (cell, &value) in state_row.iter_mut().zip(field_row.iter()) {
+            *cell = ((value.clamp(0, ONE) * 15 + ONE / 2) / ONE) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same model in floating point, to check the fixed point one by
+    struct Reference {
+        radius: i32,
+        mu: f64,
+        sigma: f64,
+        dt: f64,
+    }
+
+    impl Reference {
+        fn from(lenia: &Lenia) -> Reference {
+            Reference {
+                radius: lenia.radius as i32,
+                mu: to_float(lenia.mu),
+                sigma: to_float(lenia.sigma),
+                dt: to_float(lenia.dt),
+            }
+        }
+
+        fn kernel(&self) -> Vec<(i32, i32, f64)> {
+            let mut kernel = Vec::new();
+            let mut total = 0.0;
+            for dr in -self.radius..=self.radius {
+                for dc in -self.radius..=self.radius {
+                    let r = ((dr * dr + dc * dc) as f64).sqrt() / self.radius as f64;
+                    if r < 1.0 {
+                        let weight = (4.0 * r * (1.0 - r)).powi(4);
+                        kernel.push((dr, dc, weight));
+                        total += weight;
+                    }
+                }
+            }
+            for entry in kernel.iter_mut() {
+                entry.2 /= total;
+            }
+            kernel
+        }
+
+        fn growth(&self, potential: f64) -> f64 {
+            let closeness = 1.0 - (potential - self.mu).powi(2) / (9.0 * self.sigma.powi(2));
+            2.0 * closeness.max(0.0).powi(4) - 1.0
+        }
+
+        fn step(&self, field: &[[f64; 16]; 8]) -> [[f64; 16]; 8] {
+            let kernel = self.kernel();
+            let mut next = [[0.0; 16]; 8];
+            for row in 0..8 {
+                for col in 0..16 {
+                    let mut potential = 0.0;
+                    for &(dr, dc, weight) in &kernel {
+                        let r = (row as i32 + dr).rem_euclid(8) as usize;
+                        let c = (col as i32 + dc).rem_euclid(16) as usize;
+                        potential += weight * field[r][c];
+                    }
+                    let grown = field[row][col] + self.dt * self.growth(potential);
+                    next[row][col] = grown.clamp(0.0, 1.0);
+                }
+            }
+            next
+        }
+    }
+
+    fn to_float(value: Fixed) -> f64 {
+        value as f64 / ONE as f64
+    }
+
+    #[test]
+    fn fixed_point_fractions() {
+        assert_eq!(fixed(1, 1), ONE);
+        assert_eq!(fixed(1, 2), ONE / 2);
+        assert_eq!(fixed(-3, 4), -3 * ONE / 4);
+        assert_eq!(mul(fixed(3, 2), fixed(1, 4)), fixed(3, 8));
+        for n in [0, 1, 2, 3, 4, 15, 16, 17, 1 << 40, u64::MAX] {
+            let root = isqrt(n) as u128;
+            assert!(root * root <= n as u128 && (root + 1) * (root + 1) > n as u128);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let (mu, sigma, dt) = (fixed(15, 100), fixed(2, 100), fixed(1, 10));
+        assert_eq!(Lenia::try_new(0, mu, sigma, dt), Err(LeniaError::BadRadius));
+        assert_eq!(
+            Lenia::try_new(MAX_RADIUS + 1, mu, sigma, dt),
+            Err(LeniaError::BadRadius)
+        );
+        assert_eq!(Lenia::try_new(3, -1, sigma, dt), Err(LeniaError::BadMu));
+        assert_eq!(
+            Lenia::try_new(3, ONE + 1, sigma, dt),
+            Err(LeniaError::BadMu)
+        );
+        assert_eq!(
+            Lenia::try_new(3, Fixed::MAX, sigma, dt),
+            Err(LeniaError::BadMu)
+        );
+        assert_eq!(Lenia::try_new(3, mu, 0, dt), Err(LeniaError::BadSigma));
+        assert_eq!(
+            Lenia::try_new(3, mu, ONE + 1, dt),
+            Err(LeniaError::BadSigma)
+        );
+        assert_eq!(
+            Lenia::try_new(3, mu, Fixed::MAX, dt),
+            Err(LeniaError::BadSigma)
+        );
+        assert_eq!(
+            Lenia::try_new(3, mu, sigma, 0),
+            Err(LeniaError::BadTimeStep)
+        );
+        assert_eq!(
+            Lenia::try_new(3, mu, sigma, ONE + 1),
+            Err(LeniaError::BadTimeStep)
+        );
+        assert!(Lenia::try_new(MAX_RADIUS, mu, sigma, ONE).is_ok());
+        assert!(Lenia::try_new(3, 0, ONE, dt).is_ok());
+        assert!(Lenia::try_new(3, ONE, sigma, dt).is_ok());
+    }
+
+    #[test]
+    fn kernel_is_a_ring_that_adds_up_to_one() {
+        for radius in MIN_RADIUS..=MAX_RADIUS {
+            let lenia = Lenia::new(radius, fixed(15, 100), fixed(2, 100), fixed(1, 10));
+            let reach = radius as isize;
+            let reference = Reference::from(&lenia);
+
+            let mut total = 0;
+            for (dr, dc, weight) in reference.kernel() {
+                let fixed_weight = lenia.weight(dr as isize, dc as isize);
+                assert!((to_float(fixed_weight) - weight).abs() < 0.001);
+                assert_eq!(fixed_weight, lenia.weight(dc as isize, -dr as isize));
+                total += fixed_weight;
+            }
+            // each weight is rounded, so the total can be off by a little
+            assert!((ONE - total).abs() < 100, "{}", total);
+
+            assert_eq!(lenia.weight(0, 0), 0);
+            assert_eq!(lenia.weight(reach, 0), 0);
+            assert_eq!(lenia.weight(reach + 1, 0), 0);
+        }
+    }
+
+    #[test]
+    fn growth_matches_the_reference() {
+        let lenia = Lenia::PANEL;
+        let reference = Reference::from(&lenia);
+        for step in 0..=100 {
+            let potential = fixed(step, 200);
+            let expected = reference.growth(to_float(potential));
+            let growth = to_float(lenia.growth(potential));
+            assert!((growth - expected).abs() < 0.001, "{}", step);
+        }
+        assert_eq!(lenia.growth(lenia.mu), ONE);
+        assert_eq!(lenia.growth(0), -ONE);
+
+        // far off totals don't overflow on the way to no growth
+        let widest = Lenia::new(3, ONE, ONE, fixed(1, 10));
+        for potential in [Fixed::MIN, -ONE, 0, ONE, Fixed::MAX] {
+            assert_eq!(lenia.growth(potential), -ONE);
+        }
+        assert_eq!(widest.growth(Fixed::MIN), -ONE);
+        assert_eq!(widest.growth(ONE), ONE);
+    }
+
+    #[test]
+    fn steps_match_the_reference() {
+        let lenia = Lenia::PANEL;
+        let reference = Reference::from(&lenia);
+        let mut field = [[0; 16]; 8];
+        scatter(&mut field, &mut Rng::new(1));
+
+        // small differences grow from one generation to the next, the way
+        // they do in any chaotic system, so each generation is checked
+        // starting from where the fixed point one got to
+        let mut worst: f64 = 0.0;
+        for generation in 0..100 {
+            let expected = reference.step(&field.map(|row| row.map(to_float)));
+            step_field(&mut field, &lenia);
+            for (&value, &want) in field.iter().flatten().zip(expected.iter().flatten()) {
+                worst = worst.max((to_float(value) - want).abs());
+            }
+            assert!(worst < 0.001, "generation {}: off by {}", generation, worst);
+        }
+    }
+
+    #[test]
+    fn panel_parameters_keep_moving() {
+        let lenia = Lenia::PANEL;
+        let mut field = [[0; 16]; 8];
+        scatter(&mut field, &mut Rng::new(2));
+        for _ in 0..300 {
+            step_field(&mut field, &lenia);
+        }
+
+        let before = field;
+        for _ in 0..20 {
+            step_field(&mut field, &lenia);
+        }
+        let mass: i64 = field.iter().flatten().map(|&v| v as i64).sum();
+        assert!(mass > 0 && mass < 16 * 8 * ONE as i64 / 2);
+        assert_ne!(field, before);
+    }
+
+    #[test]
+    fn values_quantize_to_brightness_levels() {
+        let mut field = [[0; 3]; 1];
+        field[0] = [0, ONE / 2, ONE];
+        let mut state = [[0; 3]; 1];
+        quantize(&field, &mut state);
+        assert_eq!(state, [[0, 8, 15]]);
+
+        let mut field = [[0; 16]; 1];
+        for (i, value) in field[0].iter_mut().enumerate() {
+            *value = fixed(i as i32, 15);
+        }
+        let mut state = [[0; 16]; 1];
+        quantize(&field, &mut state);
+        for (i, &level) in state[0].iter().enumerate() {
+            assert_eq!(level, i as u8);
+            assert_eq!(Lenia::PANEL.brightness(level), level);
+        }
+    }
+}
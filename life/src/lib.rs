@@ -0,0 +1,801 @@
+//! The game of life engine, kept separate from the firmware so it can be
+//! tested on the host
+#![cfg_attr(not(test), no_std)]
+// grid code reads more clearly with explicit row and column indices
+#![allow(clippy::needless_range_loop)]
+
+pub mod age;
+pub mod cyclic;
+pub mod elementary;
+pub mod fade;
+pub mod hensel;
+pub mod lenia;
+pub mod life106;
+pub mod ltl;
+pub mod margolus;
+pub mod neighborhood;
+pub mod packed;
+pub mod plaintext;
+pub mod reversible;
+pub mod rle;
+pub mod rng;
+pub mod rule;
+pub mod sand;
+pub mod species;
+pub mod stochastic;
+pub mod tiled;
+pub mod topology;
+pub mod turmite;
+pub mod viewport;
+pub mod wireworld;
+
+pub use age::AgeGradient;
+pub use cyclic::CyclicRule;
+pub use elementary::Elementary;
+pub use fade::Fade;
+pub use hensel::{Configurations, Isotropic};
+pub use lenia::Lenia;
+pub use ltl::LtlRule;
+pub use margolus::Margolus;
+pub use packed::PackedGrid;
+pub use reversible::{Direction, Reversible};
+pub use rng::Rng;
+pub use rule::{Neighborhood, Rule};
+pub use sand::Sand;
+pub use species::Species;
+pub use stochastic::{Chance, Stochastic};
+pub use tiled::TiledUniverse;
+pub use topology::{Topology, Twist};
+pub use turmite::{Ant, Turmite};
+pub use viewport::{Follow, Viewport};
+pub use wireworld::WireWorld;
+
+/// A grid of cells `W` wide and `H` tall, stored one row after another
+///
+/// Both the simulation state and the display image use this shape, so a
+/// state can only be shown on an image of the same size.
+pub type Grid<const W: usize, const H: usize> = [[u8; W]; H];
+
+/// Something that knows how bright each cell state should be on the display
+pub trait Palette {
+    /// The brightness, from 0 to 15, to show a cell with the given value
+    fn brightness(&self, cell: u8) -> u8;
+}
+
+/// Cells keep their state in the low nibble. The high nibble is only used
+/// while stepping, to hold the next state.
+pub(crate) const STATE: u8 = 0x0f;
+
+/// 1 if the cell counts as a live neighbor, 0 otherwise
+pub(crate) fn alive(cell: u8) -> u8 {
+    (cell & STATE == 1) as u8
+}
+
+/// Count the live neighbors of a cell, treating everything off the edge of
+/// the grid as dead
+pub fn count_neighbors_bounded<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    row: usize,
+    col: usize,
+) -> u8 {
+    let mut total = 0;
+    for r in row.saturating_sub(1)..=core::cmp::min(H - 1, row + 1) {
+        for c in col.saturating_sub(1)..=core::cmp::min(W - 1, col + 1) {
+            if r == row && c == col {
+                continue;
+            }
+            total += alive(state[r][c]);
+        }
+    }
+    total
+}
+
+/// Count the live neighbors of a cell, wrapping around the edges of the grid
+pub fn count_neighbors_torus<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    row: usize,
+    col: usize,
+) -> u8 {
+    let mut total = 0;
+    for roff in H - 1..=H + 1 {
+        for coff in W - 1..=W + 1 {
+            if roff == H && coff == W {
+                continue;
+            }
+
+            let r = (row + roff) % H;
+            let c = (col + coff) % W;
+            total += alive(state[r][c]);
+        }
+    }
+    total
+}
+
+/// Count the live neighbors of a cell on any topology
+pub fn count_neighbors<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    topology: &Topology,
+    row: usize,
+    col: usize,
+) -> u8 {
+    match topology {
+        Topology::Plane => count_neighbors_bounded(state, row, col),
+        Topology::Torus => count_neighbors_torus(state, row, col),
+        _ => {
+            let mut total = 0;
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    if dr == 0 && dc == 0 {
+                        continue;
+                    }
+                    let r = row as isize + dr;
+                    let c = col as isize + dc;
+                    if let Some((r, c)) = topology.locate(r, c, W, H) {
+                        total += alive(state[r][c]);
+                    }
+                }
+            }
+            total
+        }
+    }
+}
+
+/// Count the live neighbors of a cell in any neighborhood on any topology
+pub fn count_neighborhood<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    topology: &Topology,
+    neighborhood: &Neighborhood,
+    row: usize,
+    col: usize,
+) -> u8 {
+    if *neighborhood == Neighborhood::Moore {
+        return count_neighbors(state, topology, row, col);
+    }
+
+    let mut total = 0;
+    for &(dr, dc) in neighborhood.offsets(row) {
+        let r = row as isize + dr;
+        let c = col as isize + dc;
+        if let Some((r, c)) = topology.locate(r, c, W, H) {
+            total += alive(state[r][c]);
+        }
+    }
+    total
+}
+
+/// Find which of a cell's Moore neighbors are alive, as a byte laid out the
+/// way the `hensel` module describes
+pub fn neighbor_configuration<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    topology: &Topology,
+    row: usize,
+    col: usize,
+) -> u8 {
+    let mut configuration = 0;
+    for (bit, &(dr, dc)) in Neighborhood::Moore.offsets(row).iter().enumerate() {
+        let r = row as isize + dr;
+        let c = col as isize + dc;
+        if let Some((r, c)) = topology.locate(r, c, W, H) {
+            configuration |= alive(state[r][c]) << bit;
+        }
+    }
+    configuration
+}
+
+/// Find the state a cell will be in next generation
+pub(crate) fn next_cell<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    rule: &Rule,
+    topology: &Topology,
+    row: usize,
+    col: usize,
+) -> u8 {
+    let cell = state[row][col] & STATE;
+    if rule.isotropic.is_some() {
+        let configuration = neighbor_configuration(state, topology, row, col);
+        rule.next_configuration(cell, configuration)
+    } else {
+        let neighbors = count_neighborhood(state, topology, &rule.neighborhood, row, col);
+        rule.next(cell, neighbors)
+    }
+}
+
+/// Advance the simulation by one generation
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &Rule,
+    topology: &Topology,
+) {
+    // we can't allocate, so use the high nibble to hold what will happen in
+    // the next iteration
+    for row in 0..H {
+        for col in 0..W {
+            let next = next_cell(state, rule, topology, row, col);
+            state[row][col] |= next << 4;
+        }
+    }
+
+    // shift the next states down into place
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+/// Write the simulation state into a display image of the same size
+pub fn show_state<P: Palette, const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    image: &mut Grid<W, H>,
+    palette: &P,
+) {
+    for (image_row, state_row) in image.iter_mut().zip(state.iter()) {
+        for (pixel, &cell) in image_row.iter_mut().zip(state_row.iter()) {
+            *pixel = palette.brightness(cell);
+        }
+    }
+}
+
+/// Read a decimal number, returning it and the index just past it
+pub(crate) const fn parse_number(bytes: &[u8], start: usize) -> Option<(usize, usize)> {
+    let mut i = start;
+    let mut n: usize = 0;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        n = n
+            .saturating_mul(10)
+            .saturating_add((bytes[i] - b'0') as usize);
+        i += 1;
+    }
+    if i == start {
+        None
+    } else {
+        Some((n, i))
+    }
+}
+
+/// Read a letter followed by a number, which has to be followed by the
+/// separator or the end of the string, returning the number and the index
+/// just past it
+pub(crate) const fn labelled(
+    bytes: &[u8],
+    start: usize,
+    label: u8,
+    separator: u8,
+) -> Option<(usize, usize)> {
+    if start >= bytes.len() || !bytes[start].eq_ignore_ascii_case(&label) {
+        return None;
+    }
+    let (n, i) = match parse_number(bytes, start + 1) {
+        Some(n) => n,
+        None => return None,
+    };
+    if i < bytes.len() && bytes[i] != separator {
+        return None;
+    }
+    Some((n, i))
+}
+
+/// Draw a grid from text, with `cells` pairing each character with the
+/// state it stands for, panicking if a row is the wrong length or has a
+/// character that isn't in `cells`
+pub(crate) const fn draw<const W: usize, const H: usize>(
+    rows: [&str; H],
+    cells: &[(u8, u8)],
+) -> Grid<W, H> {
+    let mut state = [[0; W]; H];
+    let mut row = 0;
+    while row < H {
+        let bytes = rows[row].as_bytes();
+        if bytes.len() != W {
+            panic!("drawing row is the wrong length");
+        }
+        let mut col = 0;
+        while col < W {
+            let mut i = 0;
+            while i < cells.len() && cells[i].0 != bytes[col] {
+                i += 1;
+            }
+            if i == cells.len() {
+                panic!("unknown cell in drawing");
+            }
+            state[row][col] = cells[i].1;
+            col += 1;
+        }
+        row += 1;
+    }
+    state
+}
+
+/// Fill a grid with a deterministic mess of live cells, about a third of
+/// them alive
+#[cfg(test)]
+pub(crate) fn soup<const W: usize, const H: usize>(seed: u32) -> Grid<W, H> {
+    let mut state = [[0; W]; H];
+    let mut rng = Rng::new(seed);
+    for cell in state.iter_mut().flatten() {
+        *cell = rng.chance(1, 3) as u8;
+    }
+    state
+}
+
+/// Count the live cells
+#[cfg(test)]
+pub(crate) fn population<const W: usize, const H: usize>(state: &Grid<W, H>) -> usize {
+    state.iter().flatten().filter(|&&c| c == 1).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: [&str; 8]) -> Grid<16, 8> {
+        let mut state = [[0; 16]; 8];
+        for (row, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                state[row][col] = if c == 'o' { 1 } else { 0 };
+            }
+        }
+        state
+    }
+
+    #[test]
+    fn bounded_counts_stop_at_the_edge() {
+        let state = [[1; 16]; 8];
+        assert_eq!(count_neighbors_bounded(&state, 0, 0), 3);
+        assert_eq!(count_neighbors_bounded(&state, 0, 5), 5);
+        assert_eq!(count_neighbors_bounded(&state, 7, 15), 3);
+        assert_eq!(count_neighbors_bounded(&state, 4, 5), 8);
+    }
+
+    #[test]
+    fn torus_counts_wrap_around() {
+        let state = [[1; 16]; 8];
+        assert_eq!(count_neighbors_torus(&state, 0, 0), 8);
+        assert_eq!(count_neighbors_torus(&state, 7, 15), 8);
+
+        let mut corners = [[0; 16]; 8];
+        corners[0][0] = 1;
+        corners[0][15] = 1;
+        corners[7][0] = 1;
+        corners[7][15] = 1;
+        assert_eq!(count_neighbors_torus(&corners, 0, 0), 3);
+        assert_eq!(count_neighbors_bounded(&corners, 0, 0), 0);
+    }
+
+    #[test]
+    fn counts_ignore_the_next_generation() {
+        let state = [[0x10; 16]; 8];
+        assert_eq!(count_neighbors_torus(&state, 3, 3), 0);
+        assert_eq!(count_neighbors_bounded(&state, 3, 3), 0);
+
+        let mut state = [[0x01; 16]; 8];
+        state[4][4] = 0x11;
+        assert_eq!(count_neighbors_torus(&state, 3, 3), 8);
+        assert_eq!(count_neighbors_bounded(&state, 3, 3), 8);
+    }
+
+    #[test]
+    fn counts_ignore_dying_cells() {
+        let state = [[2; 16]; 8];
+        assert_eq!(count_neighbors_torus(&state, 3, 3), 0);
+        assert_eq!(count_neighbors(&state, &Topology::CrossSurface, 0, 0), 0);
+        let state = [[3; 16]; 8];
+        assert_eq!(count_neighbors_bounded(&state, 3, 3), 0);
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let horizontal = grid([
+            "................",
+            "................",
+            "................",
+            "......ooo.......",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+        let vertical = grid([
+            "................",
+            "................",
+            ".......o........",
+            ".......o........",
+            ".......o........",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        let mut state = horizontal;
+        step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(state, vertical);
+        step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(state, horizontal);
+    }
+
+    #[test]
+    fn block_is_still() {
+        let block = grid([
+            "................",
+            ".oo.............",
+            ".oo.............",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+        let mut state = block;
+        step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(state, block);
+    }
+
+    #[test]
+    fn glider_wraps_around_the_torus() {
+        let glider = grid([
+            ".o..............",
+            "..o.............",
+            "ooo.............",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        // a glider moves one cell diagonally every 4 generations, so it comes
+        // back to where it started after 4 * lcm(16, 8) generations
+        let mut state = glider;
+        for _ in 0..4 * 16 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        }
+        assert_eq!(state, glider);
+
+        let mut state = glider;
+        for _ in 0..4 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        }
+        assert_ne!(state, glider);
+        assert_eq!(population(&state), 5);
+        assert_eq!(state[3][1..4], [1, 1, 1]);
+    }
+
+    #[test]
+    fn step_uses_the_given_rule() {
+        // a dead cell with 6 neighbors is only born under HighLife
+        let six = grid([
+            "................",
+            "................",
+            "...ooo..........",
+            "................",
+            "...ooo..........",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        let mut conway = six;
+        step_state(&mut conway, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(conway[3][4], 0);
+
+        let mut highlife = six;
+        step_state(&mut highlife, &Rule::HIGHLIFE, &Topology::Torus);
+        assert_eq!(highlife[3][4], 1);
+
+        // seeds kills every live cell
+        let mut seeds = six;
+        step_state(&mut seeds, &Rule::SEEDS, &Topology::Torus);
+        assert_eq!(seeds[2][3..6], [0, 0, 0]);
+    }
+
+    #[test]
+    fn glider_dies_against_the_plane_edge() {
+        let glider = grid([
+            "................",
+            "................",
+            "................",
+            "................",
+            "..............o.",
+            "...............o",
+            ".............ooo",
+            "................",
+        ]);
+
+        let mut state = glider;
+        for _ in 0..16 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Plane);
+        }
+        // it turns into a block in the corner
+        assert_eq!(population(&state), 4);
+        assert_eq!(state[6][14..], [1, 1]);
+        assert_eq!(state[7][14..], [1, 1]);
+    }
+
+    #[test]
+    fn glider_comes_back_mirrored_on_a_klein_bottle() {
+        let glider = grid([
+            ".o..............",
+            "..o.............",
+            "ooo.............",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        // after crossing the twisted top and bottom edges once, the glider
+        // is mirrored and heading down and to the left
+        let mut state = glider;
+        for _ in 0..4 * 8 {
+            step_state(
+                &mut state,
+                &Rule::CONWAY,
+                &Topology::Klein(Twist::Horizontal),
+            );
+        }
+        let mut mirrored = [[0; 16]; 8];
+        for row in 0..8 {
+            for col in 0..16 {
+                mirrored[row][col] = glider[row][(15 - col + 8) % 16];
+            }
+        }
+        assert_eq!(state, mirrored);
+    }
+
+    #[test]
+    fn general_counts_match_the_specialized_ones() {
+        let mut state = [[0; 16]; 8];
+        for (i, cell) in state.iter_mut().flatten().enumerate() {
+            *cell = ((i * 7 + i / 3) % 5 == 0) as u8;
+        }
+        for row in 0..8 {
+            for col in 0..16 {
+                assert_eq!(
+                    count_neighbors(&state, &Topology::Plane, row, col),
+                    count_neighbors_bounded(&state, row, col)
+                );
+                assert_eq!(
+                    count_neighbors(&state, &Topology::Torus, row, col),
+                    count_neighbors_torus(&state, row, col)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn show_state_lights_live_cells() {
+        let mut state = [[0; 16]; 8];
+        state[2][3] = 1;
+        let mut image = [[7; 16]; 8];
+        show_state(&state, &mut image, &Rule::CONWAY);
+        assert_eq!(image[2][3], 15);
+        assert_eq!(image.iter().flatten().filter(|&&p| p == 0).count(), 127);
+    }
+
+    #[test]
+    fn works_on_bigger_grids() {
+        fn glider<const W: usize, const H: usize>() -> Grid<W, H> {
+            let mut state = [[0; W]; H];
+            state[0][1] = 1;
+            state[1][2] = 1;
+            state[2][0] = 1;
+            state[2][1] = 1;
+            state[2][2] = 1;
+            state
+        }
+
+        // a glider crosses the whole torus in 4 * max(W, H) generations
+        let mut state: Grid<32, 16> = glider();
+        for _ in 0..4 * 32 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Torus);
+        }
+        assert_eq!(state, glider());
+
+        let mut state: Grid<64, 32> = glider();
+        for _ in 0..4 * 20 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Plane);
+        }
+        let mut moved: Grid<64, 32> = [[0; 64]; 32];
+        for row in 0..3 {
+            for col in 0..3 {
+                moved[row + 20][col + 20] = glider::<64, 32>()[row][col];
+            }
+        }
+        assert_eq!(state, moved);
+    }
+
+    #[test]
+    fn works_on_a_square_sphere() {
+        let mut state: Grid<8, 8> = [[0; 8]; 8];
+        state[0][3] = 1;
+        state[0][4] = 1;
+        state[0][5] = 1;
+
+        // the blinker straddles the joined top and left edges, so it is
+        // split across them rather than dying
+        step_state(&mut state, &Rule::CONWAY, &Topology::Sphere);
+        assert_eq!(state[0][4], 1);
+        assert_eq!(state[1][4], 1);
+        assert_eq!(state[4][0], 1);
+        assert_eq!(population(&state), 3);
+    }
+
+    #[test]
+    fn a_sphere_that_isnt_square_acts_like_a_plane() {
+        let mut state: Grid<16, 8> = soup(4);
+        let mut plane = state;
+        step_state(&mut state, &Rule::CONWAY, &Topology::Sphere);
+        step_state(&mut plane, &Rule::CONWAY, &Topology::Plane);
+        assert_eq!(state, plane);
+    }
+
+    #[test]
+    fn generations_cells_die_slowly() {
+        let mut state = grid([
+            "................",
+            "................",
+            "................",
+            "......o.........",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        // a lonely cell in Star Wars fades out over two generations
+        step_state(&mut state, &Rule::STAR_WARS, &Topology::Torus);
+        assert_eq!(state[3][6], 2);
+        step_state(&mut state, &Rule::STAR_WARS, &Topology::Torus);
+        assert_eq!(state[3][6], 3);
+        step_state(&mut state, &Rule::STAR_WARS, &Topology::Torus);
+        assert_eq!(state, [[0; 16]; 8]);
+    }
+
+    #[test]
+    fn brians_brain_moves_away_from_its_trail() {
+        let mut state = grid([
+            "................",
+            "................",
+            "................",
+            "......oo........",
+            "......oo........",
+            "................",
+            "................",
+            "................",
+        ]);
+        // the back half of a Brian's Brain glider is dying
+        state[3][6] = 2;
+        state[4][6] = 2;
+
+        step_state(&mut state, &Rule::BRIANS_BRAIN, &Topology::Torus);
+        let mut expected = [[0; 16]; 8];
+        expected[3][7] = 2;
+        expected[4][7] = 2;
+        expected[3][8] = 1;
+        expected[4][8] = 1;
+        assert_eq!(state, expected);
+
+        let mut image = [[0; 16]; 8];
+        show_state(&state, &mut image, &Rule::BRIANS_BRAIN);
+        assert_eq!(image[3][8], 15);
+        assert!(0 < image[3][7] && image[3][7] < 15);
+        assert_eq!(image[3][6], 0);
+    }
+
+    #[test]
+    fn von_neumann_counts_edges_only() {
+        let state = [[1; 16]; 8];
+        let neighborhood = Neighborhood::VonNeumann;
+        assert_eq!(
+            count_neighborhood(&state, &Topology::Torus, &neighborhood, 0, 0),
+            4
+        );
+        assert_eq!(
+            count_neighborhood(&state, &Topology::Plane, &neighborhood, 0, 0),
+            2
+        );
+        assert_eq!(
+            count_neighborhood(&state, &Topology::Plane, &neighborhood, 0, 5),
+            3
+        );
+    }
+
+    #[test]
+    fn hexagonal_counts_follow_the_row_offset() {
+        let mut state = [[0; 16]; 8];
+        // the cells up and to the right of (2, 5) and (3, 5)
+        state[1][6] = 1;
+        state[2][6] = 1;
+        let hex = Neighborhood::Hexagonal;
+        // even rows lean left, so (1, 6) isn't a neighbor of (2, 5)
+        assert_eq!(count_neighborhood(&state, &Topology::Torus, &hex, 2, 5), 1);
+        // odd rows lean right, so (2, 6) is a neighbor of (3, 5)
+        assert_eq!(count_neighborhood(&state, &Topology::Torus, &hex, 3, 5), 1);
+        assert_eq!(count_neighborhood(&state, &Topology::Torus, &hex, 3, 6), 1);
+        assert_eq!(count_neighborhood(&state, &Topology::Torus, &hex, 3, 7), 0);
+        assert_eq!(
+            count_neighborhood(&[[1; 16]; 8], &Topology::Torus, &hex, 0, 0),
+            6
+        );
+    }
+
+    #[test]
+    fn hexagonal_rules_step_on_the_offset_grid() {
+        // in B2/S34H a pair of neighbors gives birth to the two cells that
+        // touch both of them, then dies
+        let mut state = [[0; 16]; 8];
+        state[3][4] = 1;
+        state[3][5] = 1;
+        step_state(&mut state, &Rule::new("B2/S34H"), &Topology::Torus);
+
+        let mut expected = [[0; 16]; 8];
+        expected[2][5] = 1;
+        expected[4][5] = 1;
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn configurations_follow_the_hensel_layout() {
+        let mut state = [[0; 16]; 8];
+        state[2][5] = 1;
+        state[4][7] = 1;
+        assert_eq!(
+            neighbor_configuration(&state, &Topology::Plane, 3, 6),
+            0b10000001
+        );
+        assert_eq!(
+            neighbor_configuration(&state, &Topology::Plane, 3, 5),
+            0b00000010
+        );
+        // past the corner of the torus
+        state[7][15] = 1;
+        assert_eq!(neighbor_configuration(&state, &Topology::Torus, 0, 0), 0b1);
+        assert_eq!(neighbor_configuration(&state, &Topology::Plane, 0, 0), 0);
+    }
+
+    #[test]
+    fn non_totalistic_rules_look_at_the_arrangement() {
+        let blinker = grid([
+            "................",
+            "................",
+            "................",
+            "......ooo.......",
+            "................",
+            "................",
+            "................",
+            "................",
+        ]);
+
+        // the cells above and below a blinker see three neighbors in a row,
+        // which is 3i
+        let mut state = blinker;
+        step_state(&mut state, &Rule::new("B3i/S23"), &Topology::Torus);
+        let mut expected = blinker;
+        step_state(&mut expected, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(state, expected);
+
+        let mut state = blinker;
+        step_state(&mut state, &Rule::new("B3-i/S23"), &Topology::Torus);
+        let mut lonely = [[0; 16]; 8];
+        lonely[3][7] = 1;
+        assert_eq!(state, lonely);
+    }
+
+    #[test]
+    fn moore_neighborhood_counts_match() {
+        let mut state = [[0; 16]; 8];
+        for (i, cell) in state.iter_mut().flatten().enumerate() {
+            *cell = (i % 3 == 1) as u8;
+        }
+        let topologies = [Topology::Plane, Topology::Torus, Topology::CrossSurface];
+        for topology in topologies.iter() {
+            for row in 0..8 {
+                for col in 0..16 {
+                    assert_eq!(
+                        count_neighborhood(&state, topology, &Neighborhood::Moore, row, col),
+                        count_neighbors(&state, topology, row, col)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Patterns in the Life 1.06 format, a list of live cell coordinates
+//!
+//! The first line is `#Life 1.06`, and every line after it gives a live
+//! cell as its column then its row, either of which can be negative:
+//!
+//! ```text
+//! #Life 1.06
+//! 0 -1
+//! 1 0
+//! -1 1
+//! 0 1
+//! 1 1
+//! ```
+use core::fmt::{self, Write};
+
+use crate::{Grid, STATE};
+
+/// The line every Life 1.06 file starts with
+pub const HEADER: &str = "#Life 1.06";
+
+/// Reasons a Life 1.06 pattern can fail to parse or place
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Life106Error {
+    /// The first line isn't `#Life 1.06`
+    MissingHeader,
+    /// The line with this number, counting the header as 1, isn't a pair of
+    /// coordinates
+    BadLine(usize),
+    /// A cell lands off the grid at the offset the pattern is placed at
+    TooLarge,
+}
+
+/// Bring the cells of a pattern to life on a grid, with its origin `top`
+/// rows down and `left` columns across
+///
+/// If the pattern doesn't parse or doesn't fit, the grid is left as it was.
+pub fn place<const W: usize, const H: usize>(
+    text: &str,
+    state: &mut Grid<W, H>,
+    top: isize,
+    left: isize,
+) -> Result<(), Life106Error> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim_end) != Some(HEADER) {
+        return Err(Life106Error::MissingHeader);
+    }
+
+    let mut placed = *state;
+    for (i, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (x, y) = match coordinates(line) {
+            Some(cell) => cell,
+            None => return Err(Life106Error::BadLine(i + 2)),
+        };
+        let (row, col) = match (top.checked_add(y), left.checked_add(x)) {
+            (Some(row), Some(col)) => (row, col),
+            _ => return Err(Life106Error::TooLarge),
+        };
+        if row < 0 || row >= H as isize || col < 0 || col >= W as isize {
+            return Err(Life106Error::TooLarge);
+        }
+        placed[row as usize][col as usize] = 1;
+    }
+    *state = placed;
+    Ok(())
+}
+
+/// Write out the live cells of a grid, with the origin at its top left
+/// corner. Dying cells of Generations rules are left out.
+pub fn write<const W: usize, const H: usize>(
+    out: &mut impl Write,
+    state: &Grid<W, H>,
+) -> fmt::Result {
+    writeln!(out, "{}", HEADER)?;
+    for (row, cells) in state.iter().enumerate() {
+        for (col, &cell) in cells.iter().enumerate() {
+            if cell & STATE == 1 {
+                writeln!(out, "{} {}", col, row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the column and row from a line
+fn coordinates(line: &str) -> Option<(isize, isize)> {
+    let mut numbers = line.split_whitespace().map(str::parse);
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+
+    #[test]
+    fn places_a_glider_around_its_origin() {
+        let mut state = [[0; 8]; 6];
+        place(GLIDER, &mut state, 3, 5).unwrap();
+        let mut expected = [[0; 8]; 6];
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            expected[r + 2][c + 4] = 1;
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        let mut state = [[0; 16]; 8];
+        let cases = [
+            ("", Life106Error::MissingHeader),
+            ("0 0\n", Life106Error::MissingHeader),
+            ("#Life 1.05\n0 0\n", Life106Error::MissingHeader),
+            ("#Life 1.06\n0 0\n1\n", Life106Error::BadLine(3)),
+            ("#Life 1.06\n0 0 0\n", Life106Error::BadLine(2)),
+            ("#Life 1.06\n\n0 x\n", Life106Error::BadLine(3)),
+            ("#Life 1.06\n0 0\n16 0\n", Life106Error::TooLarge),
+            ("#Life 1.06\n0 -1\n", Life106Error::TooLarge),
+        ];
+        for (text, error) in cases {
+            assert_eq!(place(text, &mut state, 0, 0), Err(error), "{:?}", text);
+        }
+        // coordinates too big to add the offset to
+        assert_eq!(
+            place("#Life 1.06\n9223372036854775807 0\n", &mut state, 0, 1),
+            Err(Life106Error::TooLarge)
+        );
+        assert_eq!(
+            place("#Life 1.06\n0 -9223372036854775808\n", &mut state, -1, 0),
+            Err(Life106Error::TooLarge)
+        );
+        assert_eq!(state, [[0; 16]; 8]);
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let mut state = [[0; 16]; 8];
+        place(GLIDER, &mut state, 4, 8).unwrap();
+        // a dying cell is left out
+        state[0][0] = 2;
+
+        let mut text = String::new();
+        write(&mut text, &state).unwrap();
+        assert_eq!(text, "#Life 1.06\n8 3\n9 4\n7 5\n8 5\n9 5\n");
+
+        let mut read = [[0; 16]; 8];
+        place(&text, &mut read, 0, 0).unwrap();
+        state[0][0] = 0;
+        assert_eq!(read, state);
+    }
+}
@@ -0,0 +1,732 @@
+//! Larger than Life rules, which count live cells out to a range of more than
+//! one cell
+//!
+//! Rules are written the way Golly writes them, as in Bosco's Rule
+//! `R5,C0,M1,S34..58,B34..45,NM`: the range, the number of states, whether
+//! the middle cell counts itself, the survival and birth intervals, and the
+//! neighborhood shape (`NM` Moore, `NN` von Neumann, `NC` circular). With more
+//! than two states, cells that don't survive die slowly the same way they do
+//! in Generations rules.
+//!
+//! On the plane and the torus the counts come from a summed-area table, so
+//! the cost of a count doesn't grow with the range for Moore neighborhoods
+//! and only grows with the height of the shape for the others. The other
+//! topologies walk the whole neighborhood of every cell.
+use core::fmt;
+
+use crate::neighborhood::Shape;
+use crate::rule::{generations_brightness, MAX_STATES};
+use crate::{alive, labelled, parse_number, Grid, Palette, Topology, STATE};
+
+/// The biggest supported range. This keeps every neighborhood's count
+/// inside a `u16`.
+pub const MAX_RANGE: u8 = 100;
+
+/// A Larger than Life rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LtlRule {
+    /// How far the neighborhood reaches from the middle
+    pub range: u8,
+    /// How many states a cell can be in, 2 for rules without dying states
+    pub states: u8,
+    /// Whether a live cell counts itself as a neighbor
+    pub middle: bool,
+    /// The lowest and highest counts, inclusive, that a live cell survives
+    pub survival: (u16, u16),
+    /// The lowest and highest counts, inclusive, that a dead cell is born
+    pub birth: (u16, u16),
+    pub shape: Shape,
+}
+
+/// Reasons a Larger than Life rule can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LtlError {
+    /// The string isn't shaped like `R5,C0,M1,S34..58,B34..45,NM`
+    Malformed,
+    /// The range was 0 or more than `MAX_RANGE`
+    BadRange,
+    /// The number of states was 1 or more than `MAX_STATES`
+    BadStates,
+}
+
+impl LtlRule {
+    /// R5,C0,M1,S34..58,B34..45,NM
+    pub const BOSCO: LtlRule = LtlRule::new("R5,C0,M1,S34..58,B34..45,NM");
+
+    /// Parse a rule, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad rule becomes a compile error.
+    pub const fn new(rule: &str) -> LtlRule {
+        match LtlRule::parse(rule) {
+            Ok(rule) => rule,
+            Err(_) => panic!("invalid Larger than Life rule"),
+        }
+    }
+
+    /// Parse a rule in Golly's notation. The letters may be lowercase and
+    /// the neighborhood may be left off, in which case it is Moore.
+    pub const fn parse(rule: &str) -> Result<LtlRule, LtlError> {
+        let bytes = rule.as_bytes();
+
+        let (range, i) = match labelled(bytes, 0, b'R', b',') {
+            Some(n) => n,
+            None => return Err(LtlError::Malformed),
+        };
+        let (states, i) = match labelled(bytes, i + 1, b'C', b',') {
+            Some(n) => n,
+            None => return Err(LtlError::Malformed),
+        };
+        let (middle, i) = match labelled(bytes, i + 1, b'M', b',') {
+            Some(n) => n,
+            None => return Err(LtlError::Malformed),
+        };
+        let (survival, i) = match interval(bytes, i + 1, b'S') {
+            Some(n) => n,
+            None => return Err(LtlError::Malformed),
+        };
+        let (birth, i) = match interval(bytes, i + 1, b'B') {
+            Some(n) => n,
+            None => return Err(LtlError::Malformed),
+        };
+
+        let mut i = i;
+        let mut shape = Shape::Moore;
+        if i < bytes.len() {
+            if i + 3 != bytes.len() || bytes[i] != b',' || !bytes[i + 1].eq_ignore_ascii_case(&b'N')
+            {
+                return Err(LtlError::Malformed);
+            }
+            shape = match bytes[i + 2].to_ascii_uppercase() {
+                b'M' => Shape::Moore,
+                b'N' => Shape::VonNeumann,
+                b'C' => Shape::Circular,
+                _ => return Err(LtlError::Malformed),
+            };
+            i += 3;
+        }
+        if i != bytes.len() || middle > 1 {
+            return Err(LtlError::Malformed);
+        }
+
+        if range == 0 || range > MAX_RANGE as usize {
+            return Err(LtlError::BadRange);
+        }
+        // Golly uses both C0 and C2 for rules without dying states
+        let states = if states == 0 { 2 } else { states };
+        if states < 2 || states > MAX_STATES as usize {
+            return Err(LtlError::BadStates);
+        }
+
+        Ok(LtlRule {
+            range: range as u8,
+            states: states as u8,
+            middle: middle == 1,
+            survival,
+            birth,
+            shape,
+        })
+    }
+
+    /// Find the state a cell will be in next generation
+    pub fn next(&self, state: u8, count: u16) -> u8 {
+        let within = |(low, high): (u16, u16)| low <= count && count <= high;
+        match state {
+            0 => within(self.birth) as u8,
+            1 if within(self.survival) => 1,
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Palette for LtlRule {
+    fn brightness(&self, cell: u8) -> u8 {
+        generations_brightness(self.states, cell)
+    }
+}
+
+impl fmt::Display for LtlRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = if self.states == 2 { 0 } else { self.states };
+        let shape = match self.shape {
+            Shape::Moore => 'M',
+            Shape::VonNeumann => 'N',
+            Shape::Circular => 'C',
+        };
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            states,
+            self.middle as u8,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            shape
+        )
+    }
+}
+
+/// Read a letter followed by an interval like `34..58`
+const fn interval(bytes: &[u8], start: usize, label: u8) -> Option<((u16, u16), usize)> {
+    if start >= bytes.len() || !bytes[start].eq_ignore_ascii_case(&label) {
+        return None;
+    }
+    let (low, i) = match parse_number(bytes, start + 1) {
+        Some(n) => n,
+        None => return None,
+    };
+    if i + 2 > bytes.len() || bytes[i] != b'.' || bytes[i + 1] != b'.' {
+        return None;
+    }
+    let (high, i) = match parse_number(bytes, i + 2) {
+        Some(n) => n,
+        None => return None,
+    };
+    if low > u16::MAX as usize || high > u16::MAX as usize {
+        return None;
+    }
+    Some(((low as u16, high as u16), i))
+}
+
+/// Advance the simulation by one generation
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &LtlRule,
+    topology: &Topology,
+) {
+    let table = match topology {
+        Topology::Plane | Topology::Torus => Some(SummedArea::new(state)),
+        _ => None,
+    };
+    let wrap = *topology == Topology::Torus;
+
+    for row in 0..H {
+        for col in 0..W {
+            let mut count = match &table {
+                Some(table) => table.count(rule, wrap, row, col),
+                None => count_by_walking(state, rule, topology, row, col),
+            };
+            if !rule.middle {
+                count -= alive(state[row][col]) as u16;
+            }
+
+            let next = rule.next(state[row][col] & STATE, count);
+            state[row][col] |= next << 4;
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+/// Count the live cells in a neighborhood, including the middle, by looking
+/// at each one
+fn count_by_walking<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    rule: &LtlRule,
+    topology: &Topology,
+    row: usize,
+    col: usize,
+) -> u16 {
+    let range = rule.range as isize;
+    let mut total = 0;
+    for dr in -range..=range {
+        let half_width = rule
+            .shape
+            .half_width(rule.range as usize, dr.unsigned_abs()) as isize;
+        for dc in -half_width..=half_width {
+            let r = row as isize + dr;
+            let c = col as isize + dc;
+            if let Some((r, c)) = topology.locate(r, c, W, H) {
+                total += alive(state[r][c]) as u16;
+            }
+        }
+    }
+    total
+}
+
+/// The number of live cells above and to the left of each cell, inclusive
+///
+/// Grids bigger than 65535 cells can have more live cells than a `u16`
+/// holds, so the sums wrap round. Every rectangle that gets counted is part
+/// of one neighborhood, which does fit, so adding and subtracting them with
+/// wrapping still comes out right.
+struct SummedArea<const W: usize, const H: usize> {
+    sums: [[u16; W]; H],
+}
+
+impl<const W: usize, const H: usize> SummedArea<W, H> {
+    fn new(state: &Grid<W, H>) -> Self {
+        let mut sums = [[0; W]; H];
+        for row in 0..H {
+            let mut row_total: u16 = 0;
+            for col in 0..W {
+                row_total = row_total.wrapping_add(alive(state[row][col]) as u16);
+                let above = if row > 0 { sums[row - 1][col] } else { 0 };
+                sums[row][col] = row_total.wrapping_add(above);
+            }
+        }
+        SummedArea { sums }
+    }
+
+    /// Count the live cells in a rectangle, with inclusive bounds
+    fn rectangle(&self, top: usize, bottom: usize, left: usize, right: usize) -> u16 {
+        let s = &self.sums;
+        let mut total = s[bottom][right];
+        if top > 0 && left > 0 {
+            total = total.wrapping_add(s[top - 1][left - 1]);
+        }
+        if top > 0 {
+            total = total.wrapping_sub(s[top - 1][right]);
+        }
+        if left > 0 {
+            total = total.wrapping_sub(s[bottom][left - 1]);
+        }
+        total
+    }
+
+    /// Count the live cells in a neighborhood, including the middle
+    fn count(&self, rule: &LtlRule, wrap: bool, row: usize, col: usize) -> u16 {
+        let range = rule.range as usize;
+        let mut total = 0;
+
+        if rule.shape == Shape::Moore {
+            let rows = Pieces::new(row as isize - range as isize, 2 * range + 1, H, wrap);
+            let cols = Pieces::new(col as isize - range as isize, 2 * range + 1, W, wrap);
+            for &(top, bottom, row_times) in rows.iter() {
+                for &(left, right, col_times) in cols.iter() {
+                    total += row_times * col_times * self.rectangle(top, bottom, left, right);
+                }
+            }
+            return total;
+        }
+
+        for dr in -(range as isize)..=range as isize {
+            let half_width = rule.shape.half_width(range, dr.unsigned_abs());
+            let rows = Pieces::new(row as isize + dr, 1, H, wrap);
+            let cols = Pieces::new(
+                col as isize - half_width as isize,
+                2 * half_width + 1,
+                W,
+                wrap,
+            );
+            for &(top, bottom, row_times) in rows.iter() {
+                for &(left, right, col_times) in cols.iter() {
+                    total += row_times * col_times * self.rectangle(top, bottom, left, right);
+                }
+            }
+        }
+        total
+    }
+}
+
+/// A run of rows or columns split into the pieces that lie on the grid
+///
+/// On the plane the run is clipped to the grid. On the torus it wraps, and
+/// a run longer than the grid covers some cells more than once, so each
+/// piece comes with the number of times it is covered.
+struct Pieces {
+    pieces: [(usize, usize, u16); 3],
+    len: usize,
+}
+
+impl Pieces {
+    fn new(start: isize, len: usize, size: usize, wrap: bool) -> Self {
+        let mut pieces = Pieces {
+            pieces: [(0, 0, 0); 3],
+            len: 0,
+        };
+        let end = start + len as isize - 1;
+
+        if !wrap {
+            let first = start.max(0);
+            let last = end.min(size as isize - 1);
+            if first <= last {
+                pieces.push(first as usize, last as usize, 1);
+            }
+            return pieces;
+        }
+
+        let whole = len / size;
+        if whole > 0 {
+            pieces.push(0, size - 1, whole as u16);
+        }
+        let rest = len % size;
+        if rest > 0 {
+            let first = start.rem_euclid(size as isize) as usize;
+            let last = first + rest - 1;
+            if last < size {
+                pieces.push(first, last, 1);
+            } else {
+                pieces.push(first, size - 1, 1);
+                pieces.push(0, last - size, 1);
+            }
+        }
+        pieces
+    }
+
+    fn push(&mut self, first: usize, last: usize, times: u16) {
+        self.pieces[self.len] = (first, last, times);
+        self.len += 1;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(usize, usize, u16)> {
+        self.pieces[..self.len].iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{population, soup, Rule, Twist};
+
+    #[test]
+    fn parses_golly_notation() {
+        assert_eq!(
+            LtlRule::parse("R5,C0,M1,S34..58,B34..45,NM"),
+            Ok(LtlRule {
+                range: 5,
+                states: 2,
+                middle: true,
+                survival: (34, 58),
+                birth: (34, 45),
+                shape: Shape::Moore,
+            })
+        );
+        assert_eq!(
+            LtlRule::parse("r5,c2,m1,s34..58,b34..45"),
+            Ok(LtlRule::BOSCO)
+        );
+
+        let rule = LtlRule::parse("R3,C4,M0,S2..3,B3..3,NC").unwrap();
+        assert_eq!(rule.states, 4);
+        assert!(!rule.middle);
+        assert_eq!(rule.shape, Shape::Circular);
+        assert_eq!(
+            LtlRule::parse("R2,C0,M0,S1..2,B2..2,NN").map(|r| r.shape),
+            Ok(Shape::VonNeumann)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert_eq!(LtlRule::parse(""), Err(LtlError::Malformed));
+        assert_eq!(LtlRule::parse("R5,C0,M1,S34..58"), Err(LtlError::Malformed));
+        assert_eq!(
+            LtlRule::parse("R5,C0,M2,S34..58,B34..45"),
+            Err(LtlError::Malformed)
+        );
+        assert_eq!(
+            LtlRule::parse("R5,C0,M1,S34,B34..45"),
+            Err(LtlError::Malformed)
+        );
+        assert_eq!(
+            LtlRule::parse("R5,C0,M1,S34..58,B34..45,NX"),
+            Err(LtlError::Malformed)
+        );
+        assert_eq!(
+            LtlRule::parse("R5,C0,M1,S34..58,B34..45,NM,"),
+            Err(LtlError::Malformed)
+        );
+        assert_eq!(
+            LtlRule::parse("R0,C0,M1,S1..2,B1..2"),
+            Err(LtlError::BadRange)
+        );
+        assert_eq!(
+            LtlRule::parse("R101,C0,M1,S1..2,B1..2"),
+            Err(LtlError::BadRange)
+        );
+        assert_eq!(
+            LtlRule::parse("R1,C1,M1,S1..2,B1..2"),
+            Err(LtlError::BadStates)
+        );
+        assert_eq!(
+            LtlRule::parse("R1,C17,M1,S1..2,B1..2"),
+            Err(LtlError::BadStates)
+        );
+    }
+
+    #[test]
+    fn displays_in_golly_notation() {
+        assert_eq!(format!("{}", LtlRule::BOSCO), "R5,C0,M1,S34..58,B34..45,NM");
+        let rule = "R3,C4,M0,S2..3,B3..3,NC";
+        assert_eq!(format!("{}", LtlRule::new(rule)), rule);
+    }
+
+    #[test]
+    fn range_one_matches_life() {
+        let life = LtlRule::new("R1,C0,M0,S2..3,B3..3,NM");
+        let topologies = [
+            Topology::Plane,
+            Topology::Torus,
+            Topology::Klein(Twist::Vertical),
+        ];
+        for topology in topologies.iter() {
+            for seed in 1..10 {
+                let mut expected: Grid<16, 8> = soup(seed);
+                let mut state = expected;
+                for _ in 0..10 {
+                    crate::step_state(&mut expected, &Rule::CONWAY, topology);
+                    step_state(&mut state, &life, topology);
+                    assert_eq!(state, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn middle_counts_the_cell_itself() {
+        // with the middle counted, S3..4 is the same as S23 without it
+        let with_middle = LtlRule::new("R1,C0,M1,S3..4,B3..3,NM");
+        let mut expected: Grid<16, 8> = soup(3);
+        let mut state = expected;
+        for _ in 0..10 {
+            crate::step_state(&mut expected, &Rule::CONWAY, &Topology::Torus);
+            step_state(&mut state, &with_middle, &Topology::Torus);
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn table_counts_match_walking() {
+        let shapes = [Shape::Moore, Shape::VonNeumann, Shape::Circular];
+        for &shape in shapes.iter() {
+            // ranges past half the grid wrap all the way around the torus
+            for range in 1..=9 {
+                let rule = LtlRule {
+                    range,
+                    shape,
+                    ..LtlRule::BOSCO
+                };
+                let state: Grid<16, 8> = soup(range as u32);
+                let table = SummedArea::new(&state);
+                for &topology in [Topology::Plane, Topology::Torus].iter() {
+                    for row in 0..8 {
+                        for col in 0..16 {
+                            assert_eq!(
+                                table.count(&rule, topology == Topology::Torus, row, col),
+                                count_by_walking(&state, &rule, &topology, row, col),
+                                "{:?} {} {:?} ({}, {})",
+                                shape,
+                                range,
+                                topology,
+                                row,
+                                col
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tables_count_grids_with_more_cells_than_a_u16() {
+        // full of live cells, so the sums go well past 65535
+        let state = [[1; 320]; 256];
+        let table = SummedArea::new(&state);
+        let rule = LtlRule {
+            range: 5,
+            ..LtlRule::BOSCO
+        };
+        for &(row, col) in [(0, 0), (128, 160), (250, 300), (255, 319)].iter() {
+            for &topology in [Topology::Plane, Topology::Torus].iter() {
+                assert_eq!(
+                    table.count(&rule, topology == Topology::Torus, row, col),
+                    count_by_walking(&state, &rule, &topology, row, col),
+                    "{:?} ({}, {})",
+                    topology,
+                    row,
+                    col
+                );
+            }
+        }
+        assert_eq!(table.count(&rule, false, 255, 319), 36);
+        assert_eq!(table.count(&rule, true, 255, 319), 121);
+    }
+
+    #[test]
+    fn walking_a_klein_bottle_matches_its_double_cover() {
+        // two copies of a Klein bottle, the second one mirrored, make a torus
+        // twice the size, so its table counts are what walking should find
+        // however many times the neighborhood wraps
+        let state: Grid<16, 8> = soup(11);
+        let mut tall = [[0; 16]; 16];
+        let mut wide = [[0; 32]; 8];
+        for row in 0..8 {
+            for col in 0..16 {
+                tall[row][col] = state[row][col];
+                tall[row + 8][15 - col] = state[row][col];
+                wide[row][col] = state[row][col];
+                wide[7 - row][col + 16] = state[row][col];
+            }
+        }
+        let tall = SummedArea::new(&tall);
+        let wide = SummedArea::new(&wide);
+
+        for &range in [1, 5, 8, 12, 20, 100].iter() {
+            let rule = LtlRule {
+                range,
+                ..LtlRule::BOSCO
+            };
+            for row in 0..8 {
+                for col in 0..16 {
+                    assert_eq!(
+                        tall.count(&rule, true, row, col),
+                        count_by_walking(
+                            &state,
+                            &rule,
+                            &Topology::Klein(Twist::Horizontal),
+                            row,
+                            col
+                        ),
+                        "{} ({}, {})",
+                        range,
+                        row,
+                        col
+                    );
+                    assert_eq!(
+                        wide.count(&rule, true, row, col),
+                        count_by_walking(
+                            &state,
+                            &rule,
+                            &Topology::Klein(Twist::Vertical),
+                            row,
+                            col
+                        ),
+                        "{} ({}, {})",
+                        range,
+                        row,
+                        col
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_ranges_wrap_round_every_topology() {
+        // the counts have to come out the same for a pattern and its image
+        // under a symmetry of the surface
+        let state: Grid<16, 8> = soup(12);
+        let mut turned = state;
+        for row in 0..8 {
+            for col in 0..16 {
+                turned[7 - row][15 - col] = state[row][col];
+            }
+        }
+        let square: Grid<8, 8> = soup(13);
+        let mut transposed = square;
+        for row in 0..8 {
+            for col in 0..8 {
+                transposed[col][row] = square[row][col];
+            }
+        }
+
+        for &range in [8, 9, 12, 20, 100].iter() {
+            let rule = LtlRule {
+                range,
+                ..LtlRule::BOSCO
+            };
+            let t = Topology::CrossSurface;
+            for row in 0..8 {
+                for col in 0..16 {
+                    assert_eq!(
+                        count_by_walking(&state, &rule, &t, row, col),
+                        count_by_walking(&turned, &rule, &t, 7 - row, 15 - col),
+                    );
+                }
+            }
+            let t = Topology::Sphere;
+            for row in 0..8 {
+                for col in 0..8 {
+                    assert_eq!(
+                        count_by_walking(&square, &rule, &t, row, col),
+                        count_by_walking(&transposed, &rule, &t, col, row),
+                    );
+                }
+            }
+
+            // so the next generation of the image is the image of the next
+            // generation. Cells are born and survive on the middle third of
+            // the counts, so the next generation isn't empty.
+            let rule = middle_third(&state, rule, &Topology::CrossSurface);
+            let mut stepped = state;
+            let mut stepped_turned = turned;
+            step_state(&mut stepped, &rule, &Topology::CrossSurface);
+            step_state(&mut stepped_turned, &rule, &Topology::CrossSurface);
+            assert!(population(&stepped) > 0, "{}", range);
+            for row in 0..8 {
+                for col in 0..16 {
+                    assert_eq!(stepped[row][col], stepped_turned[7 - row][15 - col]);
+                }
+            }
+            let rule = middle_third(&square, rule, &Topology::Sphere);
+            let mut stepped = square;
+            let mut stepped_transposed = transposed;
+            step_state(&mut stepped, &rule, &Topology::Sphere);
+            step_state(&mut stepped_transposed, &rule, &Topology::Sphere);
+            assert!(population(&stepped) > 0, "{}", range);
+            for row in 0..8 {
+                for col in 0..8 {
+                    assert_eq!(stepped[row][col], stepped_transposed[col][row]);
+                }
+            }
+        }
+    }
+
+    /// The rule with births and survival on the middle third of the counts
+    /// a grid has
+    fn middle_third<const W: usize, const H: usize>(
+        state: &Grid<W, H>,
+        rule: LtlRule,
+        topology: &Topology,
+    ) -> LtlRule {
+        let mut counts = Vec::new();
+        for row in 0..H {
+            for col in 0..W {
+                counts.push(count_by_walking(state, &rule, topology, row, col));
+            }
+        }
+        counts.sort_unstable();
+        let band = (counts[counts.len() / 3], counts[counts.len() * 2 / 3]);
+        LtlRule {
+            middle: true,
+            survival: band,
+            birth: band,
+            ..rule
+        }
+    }
+
+    #[test]
+    fn bosco_keeps_a_blob_alive() {
+        // a solid 7x7 block has between 34 and 58 live cells around each of
+        // its inner cells, so the middle survives
+        let mut state = [[0; 32]; 32];
+        for row in 12..19 {
+            for col in 12..19 {
+                state[row][col] = 1;
+            }
+        }
+        step_state(&mut state, &LtlRule::BOSCO, &Topology::Torus);
+        assert_eq!(state[15][15], 1);
+        assert!(state.iter().flatten().any(|&c| c == 0));
+    }
+
+    #[test]
+    fn dying_states_count_down() {
+        let rule = LtlRule::new("R2,C4,M0,S20..24,B30..30,NM");
+        let mut state: Grid<16, 8> = [[0; 16]; 8];
+        state[3][3] = 1;
+        step_state(&mut state, &rule, &Topology::Torus);
+        assert_eq!(state[3][3], 2);
+        step_state(&mut state, &rule, &Topology::Torus);
+        assert_eq!(state[3][3], 3);
+        step_state(&mut state, &rule, &Topology::Torus);
+        assert_eq!(state[3][3], 0);
+        assert!(rule.brightness(2) > rule.brightness(3));
+    }
+}
@@ -0,0 +1,363 @@
+//! Block cellular automata on the Margolus neighborhood
+//!
+//! Rather than each cell looking at its neighbors, the grid is cut into 2x2
+//! blocks and each block is replaced as a whole. On even generations the
+//! blocks start at the top left corner, and on odd generations they shift one
+//! cell down and to the right, so information can cross block edges. The
+//! shifted blocks wrap around the edges the same way `count_neighbors_torus`
+//! does, which is why both sides of the grid have to be even.
+//!
+//! A block is numbered by adding up its live cells' values:
+//!
+//! ```text
+//! 1 2
+//! 4 8
+//! ```
+//!
+//! and a rule is a table giving the block each of the 16 blocks turns into.
+//! Rules are written the way Golly writes them, as in
+//! `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15`.
+use core::fmt;
+
+use crate::{parse_number, Grid, Palette, STATE};
+
+/// A block rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Margolus {
+    /// The block each block turns into
+    pub table: [u8; 16],
+}
+
+/// Reasons a block rule can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MargolusError {
+    /// The string isn't shaped like `MS,D0;1;2;...`
+    Malformed,
+    /// The table didn't have exactly 16 entries
+    WrongLength,
+    /// An entry was past 15
+    BadEntry(usize),
+}
+
+impl Margolus {
+    /// Critters, which turns every block that hasn't got exactly two live
+    /// cells inside out, and turns blocks that had three live cells round
+    /// by 180° as well. Gliders run about and bounce off each other.
+    pub const CRITTERS: Margolus = Margolus::new("MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0");
+    /// Tron, which inverts blocks that are all alive or all dead, sending
+    /// rectangles out from any disturbance
+    pub const TRON: Margolus = Margolus::new("MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0");
+    /// Fredkin and Toffoli's billiard ball machine. Balls move diagonally
+    /// and bounce off each other.
+    pub const BILLIARD_BALL: Margolus = Margolus::new("MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15");
+
+    /// Parse a rule, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad rule becomes a compile error.
+    pub const fn new(rule: &str) -> Margolus {
+        match Margolus::parse(rule) {
+            Ok(rule) => rule,
+            Err(_) => panic!("invalid block rule"),
+        }
+    }
+
+    /// Parse a rule in Golly's notation. The letters may be lowercase.
+    pub const fn parse(rule: &str) -> Result<Margolus, MargolusError> {
+        let bytes = rule.as_bytes();
+        if bytes.len() < 4
+            || !bytes[0].eq_ignore_ascii_case(&b'M')
+            || !bytes[1].eq_ignore_ascii_case(&b'S')
+            || bytes[2] != b','
+            || !bytes[3].eq_ignore_ascii_case(&b'D')
+        {
+            return Err(MargolusError::Malformed);
+        }
+
+        let mut table = [0; 16];
+        let mut entries = 0;
+        let mut i = 4;
+        loop {
+            let (block, next) = match parse_number(bytes, i) {
+                Some(n) => n,
+                None => return Err(MargolusError::Malformed),
+            };
+            if entries == 16 {
+                return Err(MargolusError::WrongLength);
+            }
+            if block > 15 {
+                return Err(MargolusError::BadEntry(block));
+            }
+            table[entries] = block as u8;
+            entries += 1;
+
+            if next == bytes.len() {
+                break;
+            }
+            if bytes[next] != b';' {
+                return Err(MargolusError::Malformed);
+            }
+            i = next + 1;
+        }
+        if entries != 16 {
+            return Err(MargolusError::WrongLength);
+        }
+
+        Margolus::from_table(table)
+    }
+
+    /// Make a rule from a table, checking every entry is a block
+    pub const fn from_table(table: [u8; 16]) -> Result<Margolus, MargolusError> {
+        let mut i = 0;
+        while i < 16 {
+            if table[i] > 15 {
+                return Err(MargolusError::BadEntry(table[i] as usize));
+            }
+            i += 1;
+        }
+        Ok(Margolus { table })
+    }
+
+    /// The rule that undoes this one, if no two blocks turn into the same
+    /// block
+    pub fn inverse(&self) -> Option<Margolus> {
+        let mut table = [16; 16];
+        for (block, &next) in self.table.iter().enumerate() {
+            if table[next as usize] != 16 {
+                return None;
+            }
+            table[next as usize] = block as u8;
+        }
+        Some(Margolus { table })
+    }
+}
+
+impl Palette for Margolus {
+    fn brightness(&self, cell: u8) -> u8 {
+        if cell == 1 {
+            15
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for Margolus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MS,D")?;
+        for (i, block) in self.table.iter().enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}", block)?;
+        }
+        Ok(())
+    }
+}
+
+/// The size of grid blocks are laid out on
+struct Blocks<const W: usize, const H: usize>;
+
+impl<const W: usize, const H: usize> Blocks<W, H> {
+    // evaluated when a grid is stepped, so an odd sized grid, which the
+    // blocks wouldn't fit round, is a compile error
+    const EVEN: () = assert!(W & 1 == 0 && H & 1 == 0, "blocks need an even sized grid");
+}
+
+/// Advance the simulation by one generation. The blocks are shifted on odd
+/// generations.
+///
+/// The grid's width and height have to be even, since the blocks wouldn't
+/// fit round the torus otherwise.
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &Margolus,
+    generation: u32,
+) {
+    let () = Blocks::<W, H>::EVEN;
+    let offset = (generation & 1) as usize;
+
+    // every cell is in exactly one block, so blocks can be replaced in place
+    for top in (offset..H).step_by(2) {
+        for left in (offset..W).step_by(2) {
+            let cells = [
+                (top, left),
+                (top, (left + 1) % W),
+                ((top + 1) % H, left),
+                ((top + 1) % H, (left + 1) % W),
+            ];
+
+            let mut block = 0;
+            for (bit, &(r, c)) in cells.iter().enumerate() {
+                block |= ((state[r][c] & STATE == 1) as usize) << bit;
+            }
+            let next = rule.table[block];
+            for (bit, &(r, c)) in cells.iter().enumerate() {
+                state[r][c] = (next >> bit) & 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{population, soup};
+
+    #[test]
+    fn parses_golly_notation() {
+        let identity = Margolus::parse("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;15");
+        assert_eq!(
+            identity,
+            Ok(Margolus {
+                table: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+            })
+        );
+        assert_eq!(
+            Margolus::parse("ms,d15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0"),
+            Ok(Margolus::TRON)
+        );
+        assert_eq!(
+            Margolus::from_table(Margolus::CRITTERS.table),
+            Ok(Margolus::CRITTERS)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert_eq!(Margolus::parse(""), Err(MargolusError::Malformed));
+        assert_eq!(
+            Margolus::parse("0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;15"),
+            Err(MargolusError::Malformed)
+        );
+        assert_eq!(
+            Margolus::parse("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;"),
+            Err(MargolusError::Malformed)
+        );
+        assert_eq!(
+            Margolus::parse("MS,D0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15"),
+            Err(MargolusError::Malformed)
+        );
+        assert_eq!(
+            Margolus::parse("MS,D0;1;2;3"),
+            Err(MargolusError::WrongLength)
+        );
+        assert_eq!(
+            Margolus::parse("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;15;0"),
+            Err(MargolusError::WrongLength)
+        );
+        assert_eq!(
+            Margolus::parse("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;16"),
+            Err(MargolusError::BadEntry(16))
+        );
+        let mut table = [0; 16];
+        table[3] = 99;
+        assert_eq!(
+            Margolus::from_table(table),
+            Err(MargolusError::BadEntry(99))
+        );
+    }
+
+    #[test]
+    fn displays_in_golly_notation() {
+        for rule in [Margolus::CRITTERS, Margolus::TRON, Margolus::BILLIARD_BALL] {
+            assert_eq!(Margolus::parse(&format!("{}", rule)), Ok(rule));
+        }
+        assert_eq!(
+            format!("{}", Margolus::BILLIARD_BALL),
+            "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15"
+        );
+    }
+
+    #[test]
+    fn blocks_follow_the_table() {
+        // a lone cell in the top left of a block moves to the bottom right
+        let mut state = [[0; 4]; 4];
+        state[0][0] = 1;
+        step_state(&mut state, &Margolus::BILLIARD_BALL, 0);
+        assert_eq!(state, [[0, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 0], [0; 4]]);
+
+        // under Tron empty blocks fill in, and the rest are left alone
+        let mut state = [[0; 4]; 4];
+        state[1][1] = 1;
+        step_state(&mut state, &Margolus::TRON, 0);
+        assert_eq!(
+            state,
+            [[0, 0, 1, 1], [0, 1, 1, 1], [1, 1, 1, 1], [1, 1, 1, 1]]
+        );
+    }
+
+    #[test]
+    fn odd_generations_shift_the_blocks_round_the_torus() {
+        // the shifted block in the corner takes in all four corners
+        let mut state = [[0; 4]; 4];
+        state[3][3] = 1;
+        step_state(&mut state, &Margolus::BILLIARD_BALL, 1);
+        assert_eq!(state[0][0], 1);
+
+        // so a lone ball keeps going the same way, wrapping round the edges
+        let mut state = [[0; 16]; 8];
+        state[2][4] = 1;
+        for generation in 0..20 {
+            step_state(&mut state, &Margolus::BILLIARD_BALL, generation);
+        }
+        let mut expected = [[0; 16]; 8];
+        expected[(2 + 20) % 8][(4 + 20) % 16] = 1;
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn billiard_balls_are_never_lost() {
+        let mut state: Grid<16, 8> = soup(3);
+        let balls = population(&state);
+        for generation in 0..100 {
+            step_state(&mut state, &Margolus::BILLIARD_BALL, generation);
+            assert_eq!(population(&state), balls);
+        }
+    }
+
+    #[test]
+    fn built_in_rules_run_backwards() {
+        for rule in [Margolus::CRITTERS, Margolus::TRON, Margolus::BILLIARD_BALL] {
+            let inverse = rule.inverse().unwrap();
+            let start: Grid<16, 8> = soup(7);
+            let mut state = start;
+            for generation in 0..50 {
+                step_state(&mut state, &rule, generation);
+            }
+            assert_ne!(state, start);
+            for generation in (0..50).rev() {
+                step_state(&mut state, &inverse, generation);
+            }
+            assert_eq!(state, start, "{}", rule);
+        }
+
+        let mut merging = Margolus::TRON.table;
+        merging[15] = 15;
+        assert_eq!(Margolus::from_table(merging).unwrap().inverse(), None);
+    }
+
+    #[test]
+    fn critters_inverts_and_turns_blocks_as_described() {
+        for block in 0..16u8 {
+            let live = block.count_ones();
+            let inverted = !block & 0xf;
+            // turning a block round swaps opposite corners, bits 0 and 3 and
+            // bits 1 and 2
+            let turned = (inverted & 1) << 3
+                | (inverted & 8) >> 3
+                | (inverted & 2) << 1
+                | (inverted & 4) >> 1;
+            let expected = match live {
+                2 => block,
+                3 => turned,
+                _ => inverted,
+            };
+            assert_eq!(
+                Margolus::CRITTERS.table[block as usize],
+                expected,
+                "{}",
+                block
+            );
+        }
+    }
+}
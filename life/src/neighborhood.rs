@@ -0,0 +1,98 @@
+//! Shapes of neighborhoods that reach more than one cell out from the center
+//!
+//! A shape is described by how far it reaches sideways on each row, which is
+//! what lets the counting code sum a row segment at a time.
+
+/// The shape of a neighborhood with a range of `r`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// Every cell within `r` rows and `r` columns
+    Moore,
+    /// Every cell within `r` steps, not counting diagonal steps
+    VonNeumann,
+    /// Every cell whose center is within `r + 1/2` of the middle
+    Circular,
+}
+
+impl Shape {
+    /// How many columns either side of the middle the shape covers, `dr`
+    /// rows up or down from the middle
+    pub const fn half_width(&self, range: usize, dr: usize) -> usize {
+        if dr > range {
+            return 0;
+        }
+        match self {
+            Shape::Moore => range,
+            Shape::VonNeumann => range - dr,
+            Shape::Circular => {
+                // the widest dc with dr^2 + dc^2 <= r^2 + r
+                let limit = range * range + range - dr * dr;
+                let mut dc = range;
+                while dc * dc > limit {
+                    dc -= 1;
+                }
+                dc
+            }
+        }
+    }
+
+    /// Check whether an offset from the middle is inside the shape
+    pub fn contains(&self, range: usize, dr: isize, dc: isize) -> bool {
+        let dr = dr.unsigned_abs();
+        dr <= range && dc.unsigned_abs() <= self.half_width(range, dr)
+    }
+
+    /// Count the cells in the shape, including the middle
+    pub const fn size(&self, range: usize) -> usize {
+        let mut total = 0;
+        let mut dr = 0;
+        while dr <= range {
+            let row = 2 * self.half_width(range, dr) + 1;
+            total += if dr == 0 { row } else { 2 * row };
+            dr += 1;
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_have_the_right_sizes() {
+        assert_eq!(Shape::Moore.size(1), 9);
+        assert_eq!(Shape::Moore.size(5), 121);
+        assert_eq!(Shape::VonNeumann.size(1), 5);
+        assert_eq!(Shape::VonNeumann.size(3), 25);
+        assert_eq!(Shape::Circular.size(1), 9);
+        assert_eq!(Shape::Circular.size(2), 21);
+        assert_eq!(Shape::Circular.size(5), 97);
+    }
+
+    #[test]
+    fn contains_matches_half_width() {
+        for shape in [Shape::Moore, Shape::VonNeumann, Shape::Circular].iter() {
+            for range in 1..6 {
+                let mut count = 0;
+                for dr in -7..=7 {
+                    for dc in -7..=7 {
+                        count += shape.contains(range, dr, dc) as usize;
+                    }
+                }
+                assert_eq!(count, shape.size(range), "{:?} {}", shape, range);
+            }
+        }
+    }
+
+    #[test]
+    fn circles_sit_between_diamonds_and_squares() {
+        for range in 1..10 {
+            for dr in 0..=range {
+                let circle = Shape::Circular.half_width(range, dr);
+                assert!(Shape::VonNeumann.half_width(range, dr) <= circle);
+                assert!(circle <= Shape::Moore.half_width(range, dr));
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! A grid stored as one bit per cell, stepped a whole row at a time
+//!
+//! Each row lives in a single machine word, with column `c` in bit `c`. To
+//! step a row, the eight neighbor rows (the rows above and below, and all
+//! three shifted one column left and right) are summed with bitwise adders,
+//! so every cell in the row gets its neighbor count at once without any
+//! division or per-cell loops.
+use core::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
+use crate::{step_state, Grid, Neighborhood, Rule, Topology};
+
+/// A machine word that can hold one row of a `PackedGrid`
+pub trait Row:
+    Copy
+    + Eq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+{
+    /// How many cells fit in one row
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Count the set bits
+    fn count_ones(self) -> u32;
+}
+
+macro_rules! impl_row {
+    ($($t:ty),*) => {
+        $(
+            impl Row for $t {
+                const BITS: usize = <$t>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_row!(u8, u16, u32, u64);
+
+/// The rule can't be run on this kind of grid, which is left as it was
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsupportedRule;
+
+/// A `W` by `H` grid packed into one `T` per row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedGrid<T, const W: usize, const H: usize> {
+    pub rows: [T; H],
+}
+
+impl<T: Row, const W: usize, const H: usize> Default for PackedGrid<T, W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Row, const W: usize, const H: usize> PackedGrid<T, W, H> {
+    // evaluated when a grid is made, so a row type that is too narrow for the
+    // width is a compile error
+    const FITS: () = assert!(
+        W <= T::BITS && W > 0,
+        "rows are too narrow for the grid width"
+    );
+
+    /// Make an empty grid
+    pub fn new() -> Self {
+        let () = Self::FITS;
+        PackedGrid { rows: [T::ZERO; H] }
+    }
+
+    /// Pack the live cells of a byte grid
+    pub fn from_grid(state: &Grid<W, H>) -> Self {
+        let mut grid = Self::new();
+        for row in 0..H {
+            for col in 0..W {
+                grid.set(row, col, state[row][col] & 0x0f == 1);
+            }
+        }
+        grid
+    }
+
+    /// Unpack into a byte grid, with 1 for live cells and 0 for dead ones
+    pub fn to_grid(&self, state: &mut Grid<W, H>) {
+        for row in 0..H {
+            for col in 0..W {
+                state[row][col] = self.get(row, col) as u8;
+            }
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        (self.rows[row] >> col) & T::ONE == T::ONE
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, alive: bool) {
+        if alive {
+            self.rows[row] = self.rows[row] | (T::ONE << col);
+        } else {
+            self.rows[row] = self.rows[row] & !(T::ONE << col);
+        }
+    }
+
+    /// Count the live cells
+    pub fn population(&self) -> u32 {
+        self.rows.iter().map(|row| row.count_ones()).sum()
+    }
+
+    /// Write the grid into a display image, the same way `show_state` does
+    pub fn show(&self, image: &mut Grid<W, H>) {
+        for row in 0..H {
+            for col in 0..W {
+                image[row][col] = if self.get(row, col) { 15 } else { 0 };
+            }
+        }
+    }
+
+    /// Advance the simulation by one generation
+    ///
+    /// Totalistic Moore rules on the plane and the torus are stepped a row at
+    /// a time. Everything else falls back to unpacking the grid and using
+    /// `step_state`.
+    ///
+    /// A packed grid only has room for live and dead cells, so Generations
+    /// rules are refused and need to use `step_state` instead.
+    pub fn step(&mut self, rule: &Rule, topology: &Topology) -> Result<(), UnsupportedRule> {
+        if rule.states != 2 {
+            return Err(UnsupportedRule);
+        }
+        let wrap = match topology {
+            _ if rule.neighborhood != Neighborhood::Moore || rule.isotropic.is_some() => None,
+            Topology::Plane => Some(false),
+            Topology::Torus => Some(true),
+            _ => None,
+        };
+        let wrap = match wrap {
+            Some(wrap) => wrap,
+            None => {
+                let mut state = [[0; W]; H];
+                self.to_grid(&mut state);
+                step_state(&mut state, rule, topology);
+                *self = Self::from_grid(&state);
+                return Ok(());
+            }
+        };
+
+        // rows are overwritten as we go, so hang on to the originals that
+        // are still needed
+        let first = self.rows[0];
+        let mut above = if wrap { self.rows[H - 1] } else { T::ZERO };
+        for row in 0..H {
+            let current = self.rows[row];
+            let below = if row + 1 < H {
+                self.rows[row + 1]
+            } else if wrap {
+                first
+            } else {
+                T::ZERO
+            };
+            self.rows[row] = Self::next_row(rule, above, current, below, wrap);
+            above = current;
+        }
+        Ok(())
+    }
+
+    /// Compute the next generation of one row from it and its neighbors
+    pub(crate) fn next_row(rule: &Rule, above: T, current: T, below: T, wrap: bool) -> T {
+        let [n0, n1, n2, n3] = neighbor_counts([
+            Self::west(above, wrap),
+            above,
+            Self::east(above, wrap),
+            Self::west(current, wrap),
+            Self::east(current, wrap),
+            Self::west(below, wrap),
+            below,
+            Self::east(below, wrap),
+        ]);
+        apply_rule(rule, current, [n0, n1, n2, n3]) & Self::row_mask()
+    }
+
+    /// Line up each cell with its western neighbor
+    fn west(row: T, wrap: bool) -> T {
+        if wrap {
+            (row << 1) | (row >> (W - 1))
+        } else {
+            row << 1
+        }
+    }
+
+    /// Line up each cell with its eastern neighbor
+    fn east(row: T, wrap: bool) -> T {
+        if wrap {
+            (row >> 1) | (row << (W - 1))
+        } else {
+            row >> 1
+        }
+    }
+
+    /// The bits of a row that hold cells
+    fn row_mask() -> T {
+        !T::ZERO >> (T::BITS - W)
+    }
+}
+
+/// Add up eight bit planes, giving the four bits of each column's total with
+/// the lowest bit first
+pub(crate) fn neighbor_counts<T: Row>(n: [T; 8]) -> [T; 4] {
+    let (sum_a, carry_a) = full_add(n[0], n[1], n[2]);
+    let (sum_b, carry_b) = full_add(n[3], n[4], n[5]);
+    let (sum_c, carry_c) = half_add(n[6], n[7]);
+
+    let (ones, carry_d) = full_add(sum_a, sum_b, sum_c);
+    let (twos_a, fours_a) = full_add(carry_a, carry_b, carry_c);
+    let (twos, fours_b) = half_add(twos_a, carry_d);
+    let (fours, eights) = half_add(fours_a, fours_b);
+
+    [ones, twos, fours, eights]
+}
+
+/// Pick out the cells that are alive next generation given their neighbor
+/// counts as bit planes
+pub(crate) fn apply_rule<T: Row>(rule: &Rule, alive: T, counts: [T; 4]) -> T {
+    let mut next = T::ZERO;
+    for n in 0..=8 {
+        let born = rule.birth & (1 << n) != 0;
+        let survives = rule.survival & (1 << n) != 0;
+        if !born && !survives {
+            continue;
+        }
+
+        let mut matches = !T::ZERO;
+        for (bit, &plane) in counts.iter().enumerate() {
+            matches = matches & if n & (1 << bit) != 0 { plane } else { !plane };
+        }
+
+        next = next
+            | match (born, survives) {
+                (true, true) => matches,
+                (true, false) => matches & !alive,
+                _ => matches & alive,
+            };
+    }
+    next
+}
+
+fn half_add<T: Row>(a: T, b: T) -> (T, T) {
+    (a ^ b, a & b)
+}
+
+fn full_add<T: Row>(a: T, b: T, c: T) -> (T, T) {
+    let partial = a ^ b;
+    (partial ^ c, (a & b) | (partial & c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soup;
+
+    fn assert_matches_step_state<T: Row, const W: usize, const H: usize>(
+        rule: &Rule,
+        topology: &Topology,
+    ) {
+        for seed in 1..20 {
+            let mut state: Grid<W, H> = soup(seed);
+            let mut packed = PackedGrid::<T, W, H>::from_grid(&state);
+            for generation in 0..30 {
+                step_state(&mut state, rule, topology);
+                packed.step(rule, topology).unwrap();
+
+                let mut unpacked = [[0; W]; H];
+                packed.to_grid(&mut unpacked);
+                assert_eq!(
+                    unpacked, state,
+                    "{} {:?} seed {} generation {}",
+                    rule, topology, seed, generation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counts_every_neighbor_total() {
+        for total in 0..=8 {
+            let mut planes = [0u16; 8];
+            for plane in planes.iter_mut().take(total) {
+                *plane = 0b101;
+            }
+            let counts = neighbor_counts(planes);
+            let bit_total: u16 = counts.iter().enumerate().map(|(i, &c)| (c & 1) << i).sum();
+            assert_eq!(bit_total as usize, total);
+            assert_eq!(counts.iter().map(|c| c & 0b10).max(), Some(0));
+        }
+    }
+
+    #[test]
+    fn matches_step_state_on_the_panel() {
+        for rule in [
+            Rule::CONWAY,
+            Rule::HIGHLIFE,
+            Rule::DAY_AND_NIGHT,
+            Rule::SEEDS,
+        ]
+        .iter()
+        {
+            assert_matches_step_state::<u16, 16, 8>(rule, &Topology::Torus);
+            assert_matches_step_state::<u16, 16, 8>(rule, &Topology::Plane);
+        }
+    }
+
+    #[test]
+    fn matches_step_state_with_spare_bits() {
+        assert_matches_step_state::<u32, 20, 10>(&Rule::CONWAY, &Topology::Torus);
+        assert_matches_step_state::<u32, 20, 10>(&Rule::CONWAY, &Topology::Plane);
+        assert_matches_step_state::<u8, 5, 3>(&Rule::CONWAY, &Topology::Torus);
+        // B0 turns on the spare bits unless they are masked off
+        let b0 = Rule::new("B0123/S1234");
+        assert_matches_step_state::<u16, 13, 6>(&b0, &Topology::Torus);
+        assert_matches_step_state::<u16, 13, 6>(&b0, &Topology::Plane);
+    }
+
+    #[test]
+    fn matches_step_state_on_big_grids() {
+        assert_matches_step_state::<u32, 32, 16>(&Rule::CONWAY, &Topology::Torus);
+        assert_matches_step_state::<u64, 64, 32>(&Rule::CONWAY, &Topology::Torus);
+        assert_matches_step_state::<u64, 64, 32>(&Rule::HIGHLIFE, &Topology::Plane);
+    }
+
+    #[test]
+    fn falls_back_for_other_topologies() {
+        assert_matches_step_state::<u16, 16, 8>(&Rule::CONWAY, &Topology::CrossSurface);
+        assert_matches_step_state::<u8, 8, 8>(&Rule::CONWAY, &Topology::Sphere);
+    }
+
+    #[test]
+    fn falls_back_for_other_neighborhoods() {
+        assert_matches_step_state::<u16, 16, 8>(&Rule::new("B2/S34H"), &Topology::Torus);
+        assert_matches_step_state::<u16, 16, 8>(&Rule::new("B1/S12V"), &Topology::Plane);
+    }
+
+    #[test]
+    fn falls_back_for_non_totalistic_rules() {
+        assert_matches_step_state::<u16, 16, 8>(&Rule::new("B3/S2-i34q"), &Topology::Torus);
+        assert_matches_step_state::<u16, 16, 8>(&Rule::new("B2-a/S12"), &Topology::Plane);
+    }
+
+    #[test]
+    fn refuses_generations_rules() {
+        let mut packed = PackedGrid::<u16, 16, 8>::from_grid(&soup(4));
+        let start = packed;
+        assert_eq!(
+            packed.step(&Rule::BRIANS_BRAIN, &Topology::Torus),
+            Err(UnsupportedRule)
+        );
+        assert_eq!(
+            packed.step(&Rule::STAR_WARS, &Topology::Sphere),
+            Err(UnsupportedRule)
+        );
+        assert_eq!(packed, start);
+    }
+
+    #[test]
+    fn round_trips_through_a_byte_grid() {
+        let state: Grid<16, 8> = soup(7);
+        let packed = PackedGrid::<u16, 16, 8>::from_grid(&state);
+        let mut unpacked = [[0; 16]; 8];
+        packed.to_grid(&mut unpacked);
+        assert_eq!(unpacked, state);
+        assert_eq!(packed.population() as usize, crate::population(&state));
+
+        let mut image = [[0; 16]; 8];
+        let mut expected = [[0; 16]; 8];
+        packed.show(&mut image);
+        crate::show_state(&state, &mut expected, &Rule::CONWAY);
+        assert_eq!(image, expected);
+    }
+}
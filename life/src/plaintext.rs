@@ -0,0 +1,153 @@
+//! Patterns in the plaintext format LifeWiki keeps in `.cells` files
+//!
+//! Each line is a row of the pattern, with `.` for a dead cell and `O` for a
+//! live one. Lines starting with `!` are comments, and the first of them
+//! usually gives the pattern's name:
+//!
+//! ```text
+//! !Name: Glider
+//! .O.
+//! ..O
+//! OOO
+//! ```
+//!
+//! Dead cells at the end of a row can be left out, so an empty line is an
+//! empty row. Some older files use `*` for live cells, which is read too.
+use core::fmt::{self, Write};
+
+use crate::{Grid, STATE};
+
+/// Reasons a plaintext pattern can fail to parse or place
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaintextError {
+    /// A character in a row that isn't a cell
+    BadCharacter(u8),
+    /// The pattern doesn't fit on the grid at the offset it is placed at
+    TooLarge,
+}
+
+/// Add the live cells of a pattern to a grid, with its top left corner `top`
+/// rows down and `left` columns across
+///
+/// Dead cells leave whatever is on the grid alone. If the pattern doesn't
+/// parse or doesn't fit, the grid is left as it was.
+pub fn place<const W: usize, const H: usize>(
+    text: &str,
+    state: &mut Grid<W, H>,
+    top: usize,
+    left: usize,
+) -> Result<(), PlaintextError> {
+    let mut placed = *state;
+    let rows = text.lines().filter(|line| !line.starts_with('!'));
+    for (r, line) in rows.enumerate() {
+        for (c, cell) in line.bytes().enumerate() {
+            let alive = match cell {
+                b'.' => false,
+                b'O' | b'*' => true,
+                _ => return Err(PlaintextError::BadCharacter(cell)),
+            };
+            let (row, col) = match (top.checked_add(r), left.checked_add(c)) {
+                (Some(row), Some(col)) if row < H && col < W => (row, col),
+                _ => return Err(PlaintextError::TooLarge),
+            };
+            if alive {
+                placed[row][col] = 1;
+            }
+        }
+    }
+    *state = placed;
+    Ok(())
+}
+
+/// Write out a grid, giving it a name if there is one
+///
+/// Every row is written in full, so the pattern comes back the same size
+/// when it is read in again. Only live cells are written as live, so dying
+/// cells of Generations rules come out dead.
+pub fn write<const W: usize, const H: usize>(
+    out: &mut impl Write,
+    state: &Grid<W, H>,
+    name: Option<&str>,
+) -> fmt::Result {
+    if let Some(name) = name {
+        writeln!(out, "!Name: {}", name)?;
+    }
+    for row in state.iter() {
+        for &cell in row.iter() {
+            out.write_char(if cell & STATE == 1 { 'O' } else { '.' })?;
+        }
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "!Name: Glider\n!The smallest spaceship\n.O.\n..O\nOOO\n";
+
+    #[test]
+    fn places_a_glider_at_an_offset() {
+        let mut state = [[0; 8]; 6];
+        place(GLIDER, &mut state, 2, 4).unwrap();
+        let mut expected = [[0; 8]; 6];
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            expected[r + 2][c + 4] = 1;
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn short_and_empty_rows_are_dead() {
+        let mut state = [[0; 3]; 4];
+        place("*\n\n..O\r\n.O", &mut state, 0, 0).unwrap();
+        assert_eq!(state, [[1, 0, 0], [0, 0, 0], [0, 0, 1], [0, 1, 0]]);
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        let mut state = [[0; 16]; 8];
+        assert_eq!(
+            place(".O.\n.x.\n", &mut state, 0, 0),
+            Err(PlaintextError::BadCharacter(b'x'))
+        );
+        assert_eq!(
+            place(GLIDER, &mut state, 0, 14),
+            Err(PlaintextError::TooLarge)
+        );
+        assert_eq!(
+            place(GLIDER, &mut state, 6, 0),
+            Err(PlaintextError::TooLarge)
+        );
+        // trailing dead cells still have to fit
+        assert_eq!(
+            place("O...............", &mut state, 0, 1),
+            Err(PlaintextError::TooLarge)
+        );
+        assert_eq!(
+            place(GLIDER, &mut state, usize::MAX, 0),
+            Err(PlaintextError::TooLarge)
+        );
+        assert_eq!(state, [[0; 16]; 8]);
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let mut state = [[0; 16]; 8];
+        place(GLIDER, &mut state, 3, 5).unwrap();
+        // a dying cell comes out dead
+        state[0][0] = 2;
+
+        let mut text = String::new();
+        write(&mut text, &state, Some("Glider")).unwrap();
+        assert!(text.starts_with("!Name: Glider\n................\n"));
+        assert_eq!(text.lines().count(), 9);
+        assert!(text.contains("\n.....OOO........\n"));
+
+        let mut read = [[0; 16]; 8];
+        place(&text, &mut read, 0, 0).unwrap();
+        state[0][0] = 0;
+        assert_eq!(read, state);
+    }
+}
@@ -0,0 +1,157 @@
+//! Reversible Life, using Fredkin's second-order trick
+//!
+//! Each generation is worked out from the two before it: a cell is alive if
+//! the rule says it should be, flipped if it was alive the generation before
+//! last. Since exclusive or undoes itself, the generation before last can be
+//! found from the two after it the same way, so any rule can be run
+//! backwards exactly, all the way to where it started.
+//!
+//! This only makes sense for rules with live and dead cells. Dying states of
+//! Generations rules count as dead.
+use crate::{Grid, Rule, Topology};
+
+/// Which way time is running
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    pub fn reversed(self) -> Direction {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
+/// The two generations a second-order simulation needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reversible<const W: usize, const H: usize> {
+    pub previous: Grid<W, H>,
+    pub current: Grid<W, H>,
+}
+
+impl<const W: usize, const H: usize> Reversible<W, H> {
+    /// Start from a seed, with nothing alive the generation before it
+    pub fn new(seed: &Grid<W, H>) -> Reversible<W, H> {
+        Reversible {
+            previous: [[0; W]; H],
+            current: *seed,
+        }
+    }
+
+    /// Advance one generation in the given direction
+    pub fn step(&mut self, rule: &Rule, topology: &Topology, direction: Direction) {
+        match direction {
+            Direction::Forward => self.forward(rule, topology),
+            Direction::Backward => self.backward(rule, topology),
+        }
+    }
+
+    /// Work out the next generation
+    pub fn forward(&mut self, rule: &Rule, topology: &Topology) {
+        let next = second_order(&self.current, &self.previous, rule, topology);
+        self.previous = self.current;
+        self.current = next;
+    }
+
+    /// Go back to the generation before, undoing `forward`
+    pub fn backward(&mut self, rule: &Rule, topology: &Topology) {
+        let before = second_order(&self.previous, &self.current, rule, topology);
+        self.current = self.previous;
+        self.previous = before;
+    }
+}
+
+/// Apply the rule to `middle`, then flip every cell alive in `other`
+fn second_order<const W: usize, const H: usize>(
+    middle: &Grid<W, H>,
+    other: &Grid<W, H>,
+    rule: &Rule,
+    topology: &Topology,
+) -> Grid<W, H> {
+    let mut next = *middle;
+    crate::step_state(&mut next, rule, topology);
+    for (cell, &flip) in next.iter_mut().flatten().zip(other.iter().flatten()) {
+        *cell = (*cell == 1) as u8 ^ (flip == 1) as u8;
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soup;
+
+    #[test]
+    fn first_step_follows_the_rule() {
+        let seed: Grid<16, 8> = soup(1);
+        let mut reversible = Reversible::new(&seed);
+        reversible.forward(&Rule::CONWAY, &Topology::Torus);
+
+        let mut expected = seed;
+        crate::step_state(&mut expected, &Rule::CONWAY, &Topology::Torus);
+        assert_eq!(reversible.current, expected);
+        assert_eq!(reversible.previous, seed);
+    }
+
+    #[test]
+    fn later_steps_flip_the_generation_before_last() {
+        // a lone cell dies under Conway, so the third generation is just the
+        // first one flipped back in
+        let mut seed = [[0; 5]; 5];
+        seed[2][2] = 1;
+        let mut reversible = Reversible::new(&seed);
+        reversible.forward(&Rule::CONWAY, &Topology::Plane);
+        assert_eq!(reversible.current, [[0; 5]; 5]);
+        reversible.forward(&Rule::CONWAY, &Topology::Plane);
+        assert_eq!(reversible.current, seed);
+    }
+
+    #[test]
+    fn stepping_back_restores_the_seed() {
+        let rules = [
+            Rule::CONWAY,
+            Rule::HIGHLIFE,
+            Rule::SEEDS,
+            Rule::BRIANS_BRAIN,
+        ];
+        for (i, rule) in rules.iter().enumerate() {
+            for topology in [Topology::Torus, Topology::Plane] {
+                let seed: Grid<16, 8> = soup(i as u32 + 2);
+                let start = Reversible::new(&seed);
+                let mut reversible = start;
+                for _ in 0..100 {
+                    reversible.step(rule, &topology, Direction::Forward);
+                }
+                assert_ne!(reversible, start);
+                for _ in 0..100 {
+                    reversible.step(rule, &topology, Direction::Backward);
+                }
+                assert_eq!(reversible, start, "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn direction_can_flip_partway() {
+        let mut reversible = Reversible::new(&soup::<16, 8>(7));
+        let mut direction = Direction::Forward;
+        for _ in 0..30 {
+            reversible.step(&Rule::CONWAY, &Topology::Torus, direction);
+        }
+        let turned = reversible;
+        direction = direction.reversed();
+        for _ in 0..50 {
+            reversible.step(&Rule::CONWAY, &Topology::Torus, direction);
+        }
+        // running back past the seed carries on into the past
+        direction = direction.reversed();
+        for _ in 0..50 {
+            reversible.step(&Rule::CONWAY, &Topology::Torus, direction);
+        }
+        assert_eq!(reversible, turned);
+    }
+}
@@ -0,0 +1,306 @@
+//! Patterns in the run length encoded format most Life software uses
+//!
+//! An RLE file starts with any number of `#` comment lines, then a header
+//! giving the size of the pattern and optionally its rule:
+//!
+//! ```text
+//! #N Glider
+//! x = 3, y = 3, rule = B3/S23
+//! bob$2bo$3o!
+//! ```
+//!
+//! After the header, `b` is a dead cell, `o` a live one and `$` the end of a
+//! row, each of which can have a count in front to repeat it. `!` ends the
+//! pattern. Dead cells at the end of a row and empty rows at the end of the
+//! pattern are left out. Generations patterns use `.` for dead cells and `A`
+//! onwards for states 1 and up instead.
+//!
+//! Golly's bounded grid suffix on the rule, as in `B3/S23:T16,8`, is skipped,
+//! since the grid the pattern goes on decides that.
+use crate::rule::{RuleError, MAX_STATES};
+use crate::{parse_number, Grid, Rule};
+
+/// What the header line says about a pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub width: usize,
+    pub height: usize,
+    /// The rule the pattern is meant for, if the header gives one
+    pub rule: Option<Rule>,
+}
+
+/// Reasons an RLE pattern can fail to parse or place
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RleError {
+    /// There is nothing but comments
+    MissingHeader,
+    /// The header isn't shaped like `x = 3, y = 3, rule = B3/S23`
+    BadHeader,
+    /// The rule in the header didn't parse
+    BadRule(RuleError),
+    /// A character in the pattern that isn't a cell, a count or an ending
+    BadCharacter(u8),
+    /// A cell past the width or height the header gives
+    OutsideHeader,
+    /// The pattern ends without a `!`
+    Unterminated,
+    /// The pattern doesn't fit on the grid at the offset it is placed at
+    TooLarge,
+}
+
+/// Read the header of a pattern
+pub fn header(rle: &str) -> Result<Header, RleError> {
+    split(rle).map(|(header, _)| header)
+}
+
+/// Add the live cells of a pattern to a grid, with its top left corner `top`
+/// rows down and `left` columns across
+///
+/// Dead cells leave whatever is on the grid alone. If the pattern doesn't
+/// parse or doesn't fit, the grid is left as it was.
+pub fn place<const W: usize, const H: usize>(
+    rle: &str,
+    state: &mut Grid<W, H>,
+    top: usize,
+    left: usize,
+) -> Result<Header, RleError> {
+    let (header, cells) = split(rle)?;
+    let fits = |start: usize, size: usize, limit: usize| match start.checked_add(size) {
+        Some(end) => end <= limit,
+        None => false,
+    };
+    if !fits(left, header.width, W) || !fits(top, header.height, H) {
+        return Err(RleError::TooLarge);
+    }
+
+    let mut placed = *state;
+    let bytes = cells.as_bytes();
+    let mut row: usize = 0;
+    let mut col = 0;
+    let mut i = 0;
+    loop {
+        let (count, next) = match parse_number(bytes, i) {
+            Some(number) => number,
+            None => (1, i),
+        };
+        i = next;
+        let cell = match bytes.get(i) {
+            Some(&cell) => cell,
+            None => return Err(RleError::Unterminated),
+        };
+        i += 1;
+        let cell_state = match cell {
+            b'!' => break,
+            b'$' => {
+                // counts saturate rather than overflow, and a row that far
+                // down is past the header anyway
+                row = row.saturating_add(count);
+                col = 0;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            b'b' | b'.' => 0,
+            b'o' => 1,
+            b'A'..=b'Z' if cell - b'A' + 1 < MAX_STATES => cell - b'A' + 1,
+            _ => return Err(RleError::BadCharacter(cell)),
+        };
+        if count == 0 {
+            continue;
+        }
+        if row >= header.height || count > header.width - col {
+            return Err(RleError::OutsideHeader);
+        }
+        if cell_state != 0 {
+            for cell in &mut placed[top + row][left + col..left + col + count] {
+                *cell = cell_state;
+            }
+        }
+        col += count;
+    }
+
+    *state = placed;
+    Ok(header)
+}
+
+/// Parse the header, and find where the cells start
+fn split(rle: &str) -> Result<(Header, &str), RleError> {
+    let mut rest = rle;
+    loop {
+        let (line, after) = match rest.find('\n') {
+            Some(end) => (&rest[..end], &rest[end + 1..]),
+            None => (rest, ""),
+        };
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            return Ok((parse_header(line)?, after));
+        }
+        if after.is_empty() {
+            return Err(RleError::MissingHeader);
+        }
+        rest = after;
+    }
+}
+
+fn parse_header(line: &str) -> Result<Header, RleError> {
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+    let mut rest = line;
+    while !rest.is_empty() {
+        let (key, after) = match rest.find('=') {
+            Some(equals) => (rest[..equals].trim(), &rest[equals + 1..]),
+            None => return Err(RleError::BadHeader),
+        };
+        // the rule comes last, and its bounded grid suffix has a comma in it
+        let end = match after.find(',') {
+            Some(comma) if key != "rule" => comma,
+            _ => after.len(),
+        };
+        let value = after[..end].trim();
+        rest = after.get(end + 1..).unwrap_or("");
+        match key {
+            "x" => width = Some(value.parse().map_err(|_| RleError::BadHeader)?),
+            "y" => height = Some(value.parse().map_err(|_| RleError::BadHeader)?),
+            "rule" => {
+                let value = match value.find(':') {
+                    Some(colon) => &value[..colon],
+                    None => value,
+                };
+                rule = Some(Rule::parse(value).map_err(RleError::BadRule)?);
+            }
+            _ => return Err(RleError::BadHeader),
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => Ok(Header {
+            width,
+            height,
+            rule,
+        }),
+        _ => Err(RleError::BadHeader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str =
+        "#N Glider\n#C The smallest spaceship\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+
+    #[test]
+    fn places_a_glider_at_an_offset() {
+        let mut state = [[0; 8]; 6];
+        let header = place(GLIDER, &mut state, 2, 4).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                width: 3,
+                height: 3,
+                rule: Some(Rule::CONWAY),
+            }
+        );
+        let mut expected = [[0; 8]; 6];
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            expected[r + 2][c + 4] = 1;
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn runs_span_rows_and_lines() {
+        // two blocks with a blank row between them, split over several
+        // lines the way long patterns are wrapped
+        let rle = "x=5,y=5\n2o$2o\n2$3b2o$\n3b2o!";
+        let mut state = [[0; 5]; 5];
+        let header = place(rle, &mut state, 0, 0).unwrap();
+        assert_eq!(header.rule, None);
+        assert_eq!(
+            state,
+            [
+                [1, 1, 0, 0, 0],
+                [1, 1, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 1, 1],
+                [0, 0, 0, 1, 1],
+            ]
+        );
+    }
+
+    #[test]
+    fn dead_cells_leave_the_grid_alone() {
+        let mut state = [[7; 3]; 1];
+        place("x = 3, y = 1\nbob!", &mut state, 0, 0).unwrap();
+        assert_eq!(state, [[7, 1, 7]]);
+    }
+
+    #[test]
+    fn reads_generations_states_and_rules() {
+        let rle = "x = 4, y = 1, rule = B2/S/3:T16,8\n.AB!";
+        let mut state = [[0; 4]; 1];
+        let header = place(rle, &mut state, 0, 0).unwrap();
+        assert_eq!(header.rule, Some(Rule::BRIANS_BRAIN));
+        assert_eq!(state, [[0, 1, 2, 0]]);
+    }
+
+    #[test]
+    fn reads_just_the_header() {
+        let header = header("#C comment\n\nx = 36, y = 9, rule = 23/3\n").unwrap();
+        assert_eq!((header.width, header.height), (36, 9));
+        assert_eq!(header.rule, Some(Rule::CONWAY));
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        let mut state = [[0; 16]; 8];
+        let cases = [
+            ("", RleError::MissingHeader),
+            ("#C nothing here\n", RleError::MissingHeader),
+            ("x = 3\nooo!", RleError::BadHeader),
+            ("x = 3, y = three\nooo!", RleError::BadHeader),
+            ("3o!", RleError::BadHeader),
+            (
+                "x = 3, y = 1, rule = B9/S\nooo!",
+                RleError::BadRule(RuleError::BadCount(9)),
+            ),
+            ("x = 3, y = 1\nozo!", RleError::BadCharacter(b'z')),
+            ("x = 3, y = 1\n4o!", RleError::OutsideHeader),
+            ("x = 3, y = 1\no$o!", RleError::OutsideHeader),
+            ("x = 3, y = 1\nooo", RleError::Unterminated),
+            ("x = 17, y = 1\no!", RleError::TooLarge),
+            // counts and sizes too big to add up
+            (
+                "x = 3, y = 1\no99999999999999999999999o!",
+                RleError::OutsideHeader,
+            ),
+            (
+                "x = 3, y = 1\n99999999999999999999999$o!",
+                RleError::OutsideHeader,
+            ),
+            ("x = 18446744073709551615, y = 1\no!", RleError::TooLarge),
+            ("x = 1, y = 18446744073709551615\no!", RleError::TooLarge),
+        ];
+        for (rle, error) in cases {
+            assert_eq!(place(rle, &mut state, 0, 0), Err(error), "{:?}", rle);
+        }
+        assert_eq!(state, [[0; 16]; 8]);
+
+        // fits on its own, but not that far across
+        assert_eq!(place(GLIDER, &mut state, 0, 14), Err(RleError::TooLarge));
+        assert_eq!(place(GLIDER, &mut state, 6, 0), Err(RleError::TooLarge));
+        assert_eq!(
+            place(GLIDER, &mut state, usize::MAX, 0),
+            Err(RleError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn failures_partway_leave_the_grid_as_it_was() {
+        let mut state = [[0; 4]; 2];
+        assert_eq!(
+            place("x = 4, y = 2\n4o$4o$o!", &mut state, 0, 0),
+            Err(RleError::OutsideHeader)
+        );
+        assert_eq!(state, [[0; 4]; 2]);
+    }
+}
@@ -0,0 +1,75 @@
+//! A small random number generator for seeding patterns
+//!
+//! This is Marsaglia's xorshift32. It is tiny and fast, and the same seed
+//! always gives the same sequence, which keeps tests repeatable. It is
+//! nowhere near good enough for anything that has to be unpredictable.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    /// Start a sequence. Xorshift gets stuck at zero, so a seed of zero is
+    /// swapped for a fixed nonzero one.
+    pub const fn new(seed: u32) -> Rng {
+        Rng {
+            state: if seed == 0 { 0x2545_f491 } else { seed },
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// A number from 0 up to but not including `n`
+    pub fn below(&mut self, n: u32) -> u32 {
+        // scaling rather than taking the remainder keeps the high bits, which
+        // are the better ones
+        ((self.next_u32() as u64 * n as u64) >> 32) as u32
+    }
+
+    /// True `numerator` times out of every `denominator`, on average
+    pub fn chance(&mut self, numerator: u32, denominator: u32) -> bool {
+        self.below(denominator) < numerator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_xorshift_sequence() {
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.next_u32(), 270369);
+        assert_eq!(rng.next_u32(), 67634689);
+        assert_eq!(Rng::new(0), Rng::new(0x2545_f491));
+    }
+
+    #[test]
+    fn stays_below_the_limit() {
+        let mut rng = Rng::new(7);
+        let mut seen = [0; 6];
+        for _ in 0..6000 {
+            seen[rng.below(6) as usize] += 1;
+        }
+        for &count in seen.iter() {
+            assert!(800 < count && count < 1200, "{:?}", seen);
+        }
+    }
+
+    #[test]
+    fn chance_hits_about_the_right_fraction() {
+        let mut rng = Rng::new(3);
+        let hits = (0..10000).filter(|_| rng.chance(1, 4)).count();
+        assert!(2300 < hits && hits < 2700, "{}", hits);
+        assert!((0..100).all(|_| !rng.chance(0, 4)));
+        assert!((0..100).all(|_| rng.chance(4, 4)));
+    }
+}
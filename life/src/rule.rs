@@ -0,0 +1,713 @@
+//! Life-like rules written in B/S notation
+//!
+//! Generations rules add a third section with the number of states, as in
+//! `B2/S345/4`. State 0 is dead and state 1 is alive. A live cell that doesn't
+//! survive starts dying, stepping through the states after 1 one generation at
+//! a time before it is dead again. Only live cells count as neighbors.
+//!
+//! A `V` or `H` on the end, as in `B2/S34H`, swaps the eight cell Moore
+//! neighborhood for the four cell von Neumann neighborhood or a six cell
+//! hexagonal one.
+//!
+//! Moore rules may also be isotropic non-totalistic, with letters after each
+//! count as in `B2-a/S12`. See the `hensel` module for what the letters mean.
+use core::fmt;
+
+use crate::hensel::{self, Configurations, Isotropic};
+use crate::Palette;
+
+/// A Life-like or Generations rule, stored as one bit per neighbor count
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// Bit `n` is set if a dead cell with `n` live neighbors comes alive. For
+    /// non-totalistic rules, it is set if any arrangement of `n` neighbors
+    /// does.
+    pub birth: u16,
+    /// Bit `n` is set if a live cell with `n` live neighbors stays alive, or
+    /// might for non-totalistic rules
+    pub survival: u16,
+    /// How many states a cell can be in, 2 for plain Life-like rules
+    pub states: u8,
+    /// Which cells count as neighbors
+    pub neighborhood: Neighborhood,
+    /// Exactly which arrangements of neighbors cause births and survivals,
+    /// for rules where the count alone isn't enough
+    pub isotropic: Option<Isotropic>,
+}
+
+/// The cells next to a cell that count as its neighbors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// All eight surrounding cells
+    Moore,
+    /// The four cells that share an edge
+    VonNeumann,
+    /// Six cells of a hexagonal grid laid out with offset rows
+    ///
+    /// Odd rows sit half a cell to the right of even rows, so a cell's
+    /// neighbors are the two beside it plus the two touching it in each of
+    /// the rows above and below. Every neighbor is right next to the cell on
+    /// the rectangular display, so patterns look like slightly squashed
+    /// versions of themselves rather than being sheared the way Golly's
+    /// hexagonal emulation draws them. Wrapping vertically needs an even
+    /// number of rows to keep the offsets lined up.
+    Hexagonal,
+}
+
+impl Neighborhood {
+    /// How many neighbors a cell has
+    pub const fn size(&self) -> u8 {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+            Neighborhood::Hexagonal => 6,
+        }
+    }
+
+    /// The offsets of a cell's neighbors. Hexagonal neighbors depend on
+    /// whether the cell is in an odd or even row.
+    pub fn offsets(&self, row: usize) -> &'static [(isize, isize)] {
+        match self {
+            Neighborhood::Moore => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+            Neighborhood::VonNeumann => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Neighborhood::Hexagonal if row & 1 == 0 => {
+                &[(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)]
+            }
+            Neighborhood::Hexagonal => &[(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)],
+        }
+    }
+}
+
+/// Reasons a rulestring can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// The string isn't shaped like `B3/S23` or `23/3`
+    Malformed,
+    /// A neighbor count was bigger than the size of the neighborhood
+    BadCount(u8),
+    /// A Hensel letter that doesn't go with the count before it
+    BadLetter(u8),
+    /// The number of states was less than 2 or more than `MAX_STATES`
+    BadStates,
+}
+
+/// The most states a Generations rule can have. Cells store their state in
+/// four bits, which also matches the brightness levels of the display.
+pub const MAX_STATES: u8 = 16;
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Rule = Rule::new("B3/S23");
+    /// B36/S23
+    pub const HIGHLIFE: Rule = Rule::new("B36/S23");
+    /// B3678/S34678
+    pub const DAY_AND_NIGHT: Rule = Rule::new("B3678/S34678");
+    /// B2/S
+    pub const SEEDS: Rule = Rule::new("B2/S");
+    /// B2/S/3
+    pub const BRIANS_BRAIN: Rule = Rule::new("B2/S/3");
+    /// B2/S345/4
+    pub const STAR_WARS: Rule = Rule::new("B2/S345/4");
+
+    /// Parse a rulestring, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad rule becomes a compile error.
+    pub const fn new(rule: &str) -> Rule {
+        match Rule::parse(rule) {
+            Ok(rule) => rule,
+            Err(_) => panic!("invalid rulestring"),
+        }
+    }
+
+    /// Parse a rulestring in either `B3/S23` or `23/3` (survival/birth)
+    /// notation, optionally followed by a number of states as in `B2/S/3`
+    /// or `345/2/4`
+    ///
+    /// The letters may be lowercase, the sections may come in either order,
+    /// and the slash between them is optional when both are labelled. The
+    /// number of states may be labelled with `C` or `G`. A `V` or `H` suffix
+    /// picks the neighborhood. Each count can be followed by Hensel letters,
+    /// or by a `-` and the letters to leave out, as in `B3/S2-i34q`.
+    pub const fn parse(rule: &str) -> Result<Rule, RuleError> {
+        let bytes = rule.as_bytes();
+
+        let neighborhood = match bytes.last() {
+            Some(b'V') | Some(b'v') => Neighborhood::VonNeumann,
+            Some(b'H') | Some(b'h') => Neighborhood::Hexagonal,
+            _ => Neighborhood::Moore,
+        };
+        let bytes = match neighborhood {
+            Neighborhood::Moore => bytes,
+            _ => bytes.split_at(bytes.len() - 1).0,
+        };
+
+        let (first_label, first_counts, first_configurations, i) = match section(bytes, 0) {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        let mut i = i;
+        let slash = i < bytes.len() && bytes[i] == b'/';
+        if slash {
+            i += 1;
+        }
+
+        let (second_label, second_counts, second_configurations, i) = match section(bytes, i) {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        let (states, i) = match state_count(bytes, i) {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+
+        if i != bytes.len() {
+            return Err(RuleError::Malformed);
+        }
+
+        let first = (first_counts, first_configurations);
+        let second = (second_counts, second_configurations);
+        let ((birth, birth_configurations), (survival, survival_configurations)) =
+            match (first_label, second_label) {
+                (Some(b'B'), Some(b'S')) => (first, second),
+                (Some(b'S'), Some(b'B')) => (second, first),
+                (None, None) if slash => (second, first),
+                _ => return Err(RuleError::Malformed),
+            };
+
+        let too_many = !0u16 << (neighborhood.size() + 1);
+        if (birth | survival) & too_many != 0 {
+            let highest = 15 - ((birth | survival).leading_zeros() as u8);
+            return Err(RuleError::BadCount(highest));
+        }
+
+        // rules whose letters add up to whole counts are kept totalistic, so
+        // they can still use the fast steppers
+        let isotropic = if totalistic(birth_configurations) && totalistic(survival_configurations) {
+            None
+        } else if let Neighborhood::Moore = neighborhood {
+            Some(Isotropic {
+                birth: birth_configurations,
+                survival: survival_configurations,
+            })
+        } else {
+            return Err(RuleError::Malformed);
+        };
+
+        Ok(Rule {
+            birth,
+            survival,
+            states,
+            neighborhood,
+            isotropic,
+        })
+    }
+
+    /// Find the state a cell will be in next generation from how many live
+    /// neighbors it has
+    ///
+    /// This only looks at the totalistic part of the rule, so non-totalistic
+    /// rules need `next_configuration` instead.
+    pub fn next(&self, state: u8, neighbors: u8) -> u8 {
+        self.advance(
+            state,
+            self.birth & (1 << neighbors) != 0,
+            self.survival & (1 << neighbors) != 0,
+        )
+    }
+
+    /// Find the state a cell will be in next generation from which of its
+    /// Moore neighbors are alive, laid out as in the `hensel` module
+    pub fn next_configuration(&self, state: u8, configuration: u8) -> u8 {
+        match &self.isotropic {
+            Some(isotropic) => self.advance(
+                state,
+                isotropic.birth.contains(configuration),
+                isotropic.survival.contains(configuration),
+            ),
+            None => self.next(state, configuration.count_ones() as u8),
+        }
+    }
+
+    /// Find a cell's next state from whether it would be born or survive
+    pub(crate) fn advance(&self, state: u8, born: bool, survives: bool) -> u8 {
+        match state {
+            0 => born as u8,
+            1 if survives => 1,
+            // a cell that doesn't survive starts dying, and dying cells keep
+            // going until they run out of states
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Palette for Rule {
+    fn brightness(&self, cell: u8) -> u8 {
+        generations_brightness(self.states, cell)
+    }
+}
+
+/// The display brightness for a cell in a rule with `states` states
+///
+/// Live cells are fully lit, and dying cells get dimmer the closer they are to
+/// dead while staying visible. States the rule doesn't have, left over from
+/// switching rules, are dark.
+pub(crate) fn generations_brightness(states: u8, state: u8) -> u8 {
+    match state {
+        0 => 0,
+        1 => 15,
+        _ if state < states => (15 * (states - state)).div_ceil(states),
+        _ => 0,
+    }
+}
+
+/// Read an optional `B` or `S` label followed by neighbor counts and their
+/// Hensel letters, returning the label, the counts as a bit set, the
+/// configurations they allow, and the index just past them
+const fn section(
+    bytes: &[u8],
+    start: usize,
+) -> Result<(Option<u8>, u16, Configurations, usize), RuleError> {
+    let mut i = start;
+    let mut label = None;
+    if i < bytes.len() {
+        let upper = bytes[i].to_ascii_uppercase();
+        if upper == b'B' || upper == b'S' {
+            label = Some(upper);
+            i += 1;
+        }
+    }
+
+    let mut counts = 0;
+    let mut configurations = Configurations::EMPTY;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        let count = bytes[i] - b'0';
+        if count > 8 {
+            return Err(RuleError::BadCount(count));
+        }
+        i += 1;
+
+        let negated = i < bytes.len() && bytes[i] == b'-';
+        if negated {
+            i += 1;
+        }
+        let mut lettered = Configurations::EMPTY;
+        let letters = i;
+        while i < bytes.len() && is_hensel_letter(bytes[i]) {
+            match Configurations::letter(count, bytes[i]) {
+                Some(class) => lettered = lettered.union(class),
+                None => return Err(RuleError::BadLetter(bytes[i])),
+            }
+            i += 1;
+        }
+
+        let all = Configurations::with_count(count);
+        let chosen = if i == letters {
+            if negated {
+                return Err(RuleError::Malformed);
+            }
+            all
+        } else if negated {
+            all.difference(lettered)
+        } else {
+            lettered
+        };
+        if !chosen.is_empty() {
+            counts |= 1 << count;
+        }
+        configurations = configurations.union(chosen);
+    }
+
+    Ok((label, counts, configurations, i))
+}
+
+const fn is_hensel_letter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'c' | b'e' | b'k' | b'a' | b'i' | b'n' | b'y' | b'q' | b'j' | b'r' | b't' | b'w' | b'z'
+    )
+}
+
+/// Whether every count is either entirely in the set or entirely out of it
+const fn totalistic(configurations: Configurations) -> bool {
+    let mut count = 0;
+    while count <= 8 {
+        let all = Configurations::with_count(count);
+        let chosen = configurations.intersection(all);
+        if !chosen.is_empty() && !all.difference(chosen).is_empty() {
+            return false;
+        }
+        count += 1;
+    }
+    true
+}
+
+/// Read an optional `/` and number of states, defaulting to 2 if there
+/// isn't one
+const fn state_count(bytes: &[u8], start: usize) -> Result<(u8, usize), RuleError> {
+    if start >= bytes.len() || bytes[start] != b'/' {
+        return Ok((2, start));
+    }
+
+    let mut i = start + 1;
+    if i < bytes.len() {
+        let upper = bytes[i].to_ascii_uppercase();
+        if upper == b'C' || upper == b'G' {
+            i += 1;
+        }
+    }
+
+    let digits = i;
+    let mut states: u32 = 0;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        states = states * 10 + (bytes[i] - b'0') as u32;
+        if states > MAX_STATES as u32 {
+            return Err(RuleError::BadStates);
+        }
+        i += 1;
+    }
+
+    if i == digits {
+        Err(RuleError::Malformed)
+    } else if states < 2 {
+        Err(RuleError::BadStates)
+    } else {
+        Ok((states as u8, i))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (birth, survival) = match &self.isotropic {
+            Some(isotropic) => (Some(isotropic.birth), Some(isotropic.survival)),
+            None => (None, None),
+        };
+        write!(f, "B")?;
+        write_counts(f, self.birth, birth)?;
+        write!(f, "/S")?;
+        write_counts(f, self.survival, survival)?;
+        if self.states > 2 {
+            write!(f, "/{}", self.states)?;
+        }
+        match self.neighborhood {
+            Neighborhood::Moore => Ok(()),
+            Neighborhood::VonNeumann => write!(f, "V"),
+            Neighborhood::Hexagonal => write!(f, "H"),
+        }
+    }
+}
+
+/// Write the counts in a section, with Hensel letters for counts that only
+/// include some of their configurations
+fn write_counts(
+    f: &mut fmt::Formatter,
+    counts: u16,
+    configurations: Option<Configurations>,
+) -> fmt::Result {
+    for n in 0..=8 {
+        if counts & (1 << n) == 0 {
+            continue;
+        }
+        write!(f, "{}", n)?;
+
+        let configurations = match configurations {
+            Some(configurations) => configurations,
+            None => continue,
+        };
+        let all = Configurations::with_count(n);
+        let chosen = configurations.intersection(all);
+        if chosen == all {
+            continue;
+        }
+
+        // write whichever of the included or excluded letters is shorter
+        let included = |letter| {
+            let class = Configurations::letter(n, letter).unwrap();
+            !chosen.intersection(class).is_empty()
+        };
+        let count = hensel::letters(n).filter(|&l| included(l)).count();
+        let negated = 2 * count > hensel::letters(n).count();
+        if negated {
+            write!(f, "-")?;
+        }
+        for letter in hensel::letters(n).filter(|&l| included(l) != negated) {
+            write!(f, "{}", letter as char)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Palette;
+
+    #[test]
+    fn parses_bs_notation() {
+        assert_eq!(
+            Rule::parse("B3/S23"),
+            Ok(Rule {
+                birth: 0b1000,
+                survival: 0b1100,
+                states: 2,
+                neighborhood: Neighborhood::Moore,
+                isotropic: None,
+            })
+        );
+        assert_eq!(Rule::parse("b36/s23"), Ok(Rule::HIGHLIFE));
+        assert_eq!(Rule::parse("B3678S34678"), Ok(Rule::DAY_AND_NIGHT));
+        assert_eq!(Rule::parse("S23/B3"), Ok(Rule::CONWAY));
+    }
+
+    #[test]
+    fn parses_empty_sections() {
+        assert_eq!(
+            Rule::parse("B2/S"),
+            Ok(Rule {
+                birth: 0b100,
+                survival: 0,
+                states: 2,
+                neighborhood: Neighborhood::Moore,
+                isotropic: None,
+            })
+        );
+        assert_eq!(
+            Rule::parse("B/S012345678"),
+            Ok(Rule {
+                birth: 0,
+                survival: 0x1ff,
+                states: 2,
+                neighborhood: Neighborhood::Moore,
+                isotropic: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_survival_birth_notation() {
+        assert_eq!(Rule::parse("23/3"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::parse("/2"), Ok(Rule::SEEDS));
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert_eq!(Rule::parse(""), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B3"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B3/B3"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("233"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B3/S23x"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B39/S23"), Err(RuleError::BadCount(9)));
+    }
+
+    #[test]
+    fn displays_as_bs_notation() {
+        assert_eq!(format!("{}", Rule::CONWAY), "B3/S23");
+        assert_eq!(format!("{}", Rule::parse("34/3678").unwrap()), "B3678/S34");
+        assert_eq!(format!("{}", Rule::SEEDS), "B2/S");
+    }
+
+    #[test]
+    fn parses_generations_rules() {
+        assert_eq!(
+            Rule::parse("B2/S345/4"),
+            Ok(Rule {
+                birth: 0b100,
+                survival: 0b111000,
+                states: 4,
+                neighborhood: Neighborhood::Moore,
+                isotropic: None,
+            })
+        );
+        assert_eq!(Rule::parse("345/2/4"), Ok(Rule::STAR_WARS));
+        assert_eq!(Rule::parse("b2/s345/c4"), Ok(Rule::STAR_WARS));
+        assert_eq!(Rule::parse("/2/3"), Ok(Rule::BRIANS_BRAIN));
+        assert_eq!(Rule::parse("B2/S/G3"), Ok(Rule::BRIANS_BRAIN));
+        assert_eq!(Rule::parse("B3/S23/2"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::parse("B3/S23/16").map(|r| r.states), Ok(16));
+    }
+
+    #[test]
+    fn rejects_bad_state_counts() {
+        assert_eq!(Rule::parse("B2/S/1"), Err(RuleError::BadStates));
+        assert_eq!(Rule::parse("B2/S/17"), Err(RuleError::BadStates));
+        assert_eq!(Rule::parse("B2/S/256"), Err(RuleError::BadStates));
+        assert_eq!(Rule::parse("B2/S/"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B2/S/C"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B2/S/3/4"), Err(RuleError::Malformed));
+    }
+
+    #[test]
+    fn displays_generations_rules() {
+        assert_eq!(format!("{}", Rule::STAR_WARS), "B2/S345/4");
+        assert_eq!(format!("{}", Rule::BRIANS_BRAIN), "B2/S/3");
+    }
+
+    #[test]
+    fn parses_neighborhood_suffixes() {
+        let hex = Rule::parse("B2/S34H").unwrap();
+        assert_eq!(hex.neighborhood, Neighborhood::Hexagonal);
+        assert_eq!(hex.birth, 0b100);
+        assert_eq!(hex.survival, 0b11000);
+
+        let von_neumann = Rule::parse("b2/s013v").unwrap();
+        assert_eq!(von_neumann.neighborhood, Neighborhood::VonNeumann);
+        assert_eq!(von_neumann.survival, 0b1011);
+
+        assert_eq!(
+            Rule::parse("B2/S/3H").map(|r| (r.states, r.neighborhood)),
+            Ok((3, Neighborhood::Hexagonal))
+        );
+        assert_eq!(
+            Rule::parse("23/3").map(|r| r.neighborhood),
+            Ok(Neighborhood::Moore)
+        );
+    }
+
+    #[test]
+    fn rejects_counts_bigger_than_the_neighborhood() {
+        assert_eq!(Rule::parse("B2/S35V"), Err(RuleError::BadCount(5)));
+        assert_eq!(Rule::parse("B27/S34H"), Err(RuleError::BadCount(7)));
+        assert!(Rule::parse("B2/S4V").is_ok());
+        assert!(Rule::parse("B2/S6H").is_ok());
+        assert_eq!(Rule::parse("V"), Err(RuleError::Malformed));
+    }
+
+    #[test]
+    fn displays_neighborhood_suffixes() {
+        assert_eq!(format!("{}", Rule::new("B2/S34H")), "B2/S34H");
+        assert_eq!(format!("{}", Rule::new("34/2/4V")), "B2/S34/4V");
+    }
+
+    #[test]
+    fn neighborhoods_have_the_right_offsets() {
+        for &neighborhood in [
+            Neighborhood::Moore,
+            Neighborhood::VonNeumann,
+            Neighborhood::Hexagonal,
+        ]
+        .iter()
+        {
+            for row in 0..2 {
+                let offsets = neighborhood.offsets(row);
+                assert_eq!(offsets.len(), neighborhood.size() as usize);
+                assert!(!offsets.contains(&(0, 0)));
+                // every neighborhood is symmetric under a half turn once the
+                // row offset is taken into account
+                for &(dr, dc) in offsets {
+                    let back = neighborhood.offsets((row as isize + dr).rem_euclid(2) as usize);
+                    assert!(
+                        back.contains(&(-dr, -dc)),
+                        "{:?} {:?}",
+                        neighborhood,
+                        (dr, dc)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_hensel_letters() {
+        let tlife = Rule::parse("B3/S2-i34q").unwrap();
+        assert_eq!(tlife.birth, 0b1000);
+        assert_eq!(tlife.survival, 0b11100);
+        let isotropic = tlife.isotropic.unwrap();
+        assert_eq!(isotropic.birth, Configurations::with_count(3));
+        // two neighbors on opposite edges don't survive, but other pairs do
+        assert!(!isotropic.survival.contains(0b01000010));
+        assert!(isotropic.survival.contains(0b00000011));
+        assert!(isotropic.survival.contains(0b10000001));
+        assert!(isotropic.survival.contains(0b00000111));
+        assert!(isotropic.survival.contains(0b00110110));
+        assert!(isotropic.survival.contains(0b00100101));
+        assert!(!isotropic.survival.contains(0b00001111));
+
+        let rule = Rule::parse("b2-a/s12").unwrap();
+        assert_eq!(rule.next_configuration(0, 0b00000011), 0);
+        assert_eq!(rule.next_configuration(0, 0b00000101), 1);
+        assert_eq!(rule.next_configuration(1, 0b00000011), 1);
+        assert_eq!(rule.next_configuration(1, 0b00000111), 0);
+    }
+
+    #[test]
+    fn letters_that_cover_a_count_stay_totalistic() {
+        assert_eq!(Rule::parse("B3/S2ceaikn3"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::parse("B3-/S23"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B3cekainyqjr/S2-3"), Err(RuleError::Malformed));
+        assert_eq!(
+            Rule::parse("B2-ceaikn/S23").map(|r| (r.birth, r.isotropic)),
+            Ok((0, None))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_hensel_letters() {
+        assert_eq!(Rule::parse("B1a/S23"), Err(RuleError::BadLetter(b'a')));
+        assert_eq!(Rule::parse("B3t/S23"), Err(RuleError::BadLetter(b't')));
+        assert_eq!(Rule::parse("B0c/S23"), Err(RuleError::BadLetter(b'c')));
+        assert_eq!(Rule::parse("B3/S2-x"), Err(RuleError::Malformed));
+        assert_eq!(Rule::parse("B2a/S34H"), Err(RuleError::Malformed));
+    }
+
+    #[test]
+    fn displays_hensel_letters() {
+        assert_eq!(format!("{}", Rule::new("B3/S2-i34q")), "B3/S2-i34q");
+        assert_eq!(format!("{}", Rule::new("B2-a/S12")), "B2-a/S12");
+        assert_eq!(format!("{}", Rule::new("B2ce3/S23")), "B2ce3/S23");
+        assert_eq!(format!("{}", Rule::new("B2cekn/S12/3")), "B2-ai/S12/3");
+        for text in ["B2in3-c/S4t5y6e78", "B3aeijqr/S1c2-k", "B7c/S6n"].iter() {
+            let rule = Rule::new(text);
+            assert_eq!(Rule::parse(&format!("{}", rule)), Ok(rule), "{}", text);
+        }
+    }
+
+    #[test]
+    fn next_applies_the_rule() {
+        assert_eq!(Rule::CONWAY.next(0, 3), 1);
+        assert_eq!(Rule::CONWAY.next(0, 2), 0);
+        assert_eq!(Rule::CONWAY.next(1, 2), 1);
+        assert_eq!(Rule::CONWAY.next(1, 4), 0);
+        assert_eq!(Rule::HIGHLIFE.next(0, 6), 1);
+    }
+
+    #[test]
+    fn dying_cells_count_down_to_dead() {
+        let rule = Rule::STAR_WARS;
+        assert_eq!(rule.next(1, 4), 1);
+        assert_eq!(rule.next(1, 2), 2);
+        // dying cells ignore their neighbors
+        assert_eq!(rule.next(2, 2), 3);
+        assert_eq!(rule.next(3, 4), 0);
+        assert_eq!(rule.next(0, 2), 1);
+    }
+
+    #[test]
+    fn dying_cells_get_dimmer() {
+        for states in 2..=MAX_STATES {
+            let rule = Rule {
+                states,
+                ..Rule::CONWAY
+            };
+            assert_eq!(rule.brightness(0), 0);
+            assert_eq!(rule.brightness(1), 15);
+            let mut last = 15;
+            for state in 2..states {
+                let level = rule.brightness(state);
+                assert!(0 < level && level < last, "{} of {}", state, states);
+                last = level;
+            }
+            assert_eq!(rule.brightness(states), 0);
+        }
+    }
+}
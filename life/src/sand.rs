@@ -0,0 +1,274 @@
+//! Falling sand, with water and walls
+//!
+//! Every cell is empty or holds one particle. Sand falls straight down if it
+//! can, and otherwise slides down to one side, so it piles up in slopes of
+//! one cell across for each cell down. Water does the same and then spreads
+//! sideways, so it levels out. Sand sinks through water, and walls never move.
+//!
+//! The edges of the grid are solid. Nothing is ever made or destroyed, only
+//! moved, and the only randomness comes from the `Rng` passed in, so the same
+//! seed always gives the same result.
+//!
+//! Scenes can be drawn as text, one string per row, with `.` for empty
+//! cells, `o` for sand, `~` for water and `#` for walls.
+use crate::rng::Rng;
+use crate::{draw, Grid, Palette, STATE};
+
+pub const EMPTY: u8 = 0;
+pub const SAND: u8 = 1;
+pub const WATER: u8 = 2;
+pub const WALL: u8 = 3;
+
+/// Marks a particle that has already moved this generation, so it isn't
+/// carried along by the sweep
+const MOVED: u8 = 0x10;
+
+/// The sand palette, and a home for the built-in scenes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sand;
+
+impl Sand {
+    /// Two shelves over a tank, for pouring things onto
+    pub const SHELVES: Grid<16, 8> = scene([
+        "................",
+        "................",
+        ".######.........",
+        "..........#####.",
+        "................",
+        "#..............#",
+        "#..............#",
+        "################",
+    ]);
+}
+
+impl Palette for Sand {
+    fn brightness(&self, cell: u8) -> u8 {
+        match cell {
+            SAND => 15,
+            WATER => 6,
+            WALL => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Whether a particle can move into a cell, pushing out whatever is there
+fn displaces(particle: u8, target: u8) -> bool {
+    target == EMPTY || (particle == SAND && target == WATER)
+}
+
+/// Move the particle at `from` to the first of `targets` it can go to,
+/// swapping it with whatever was there. Targets off the grid are skipped.
+fn try_moves<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    from: (usize, usize),
+    targets: &[(isize, isize)],
+) -> bool {
+    let particle = state[from.0][from.1];
+    for &(dr, dc) in targets {
+        let row = from.0 as isize + dr;
+        let col = from.1 as isize + dc;
+        if row < 0 || row >= H as isize || col < 0 || col >= W as isize {
+            continue;
+        }
+        let (row, col) = (row as usize, col as usize);
+        let target = state[row][col];
+        if target & MOVED == 0 && displaces(particle, target) {
+            state[row][col] = particle | MOVED;
+            state[from.0][from.1] = target | if target == EMPTY { 0 } else { MOVED };
+            return true;
+        }
+    }
+    false
+}
+
+/// Advance the simulation by one generation
+pub fn step_state<const W: usize, const H: usize>(state: &mut Grid<W, H>, rng: &mut Rng) {
+    // the bottom row goes first, so grains falling together don't block each
+    // other
+    for row in (0..H).rev() {
+        // sweep each row in a random direction so nothing drifts one way
+        let leftward = rng.chance(1, 2);
+        for i in 0..W {
+            let col = if leftward { W - 1 - i } else { i };
+            let particle = state[row][col];
+            if particle & MOVED != 0 || !(particle == SAND || particle == WATER) {
+                continue;
+            }
+
+            let side = if rng.chance(1, 2) { 1 } else { -1 };
+            if try_moves(state, (row, col), &[(1, 0), (1, side), (1, -side)]) {
+                continue;
+            }
+            if particle == WATER {
+                try_moves(state, (row, col), &[(0, side), (0, -side)]);
+            }
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell &= STATE;
+    }
+}
+
+/// Draw a scene from text, panicking if a row is the wrong length or has a
+/// character that isn't a cell
+///
+/// This is meant for consts, where a bad drawing becomes a compile error.
+pub const fn scene<const W: usize, const H: usize>(rows: [&str; H]) -> Grid<W, H> {
+    draw(
+        rows,
+        &[(b'.', EMPTY), (b'o', SAND), (b'~', WATER), (b'#', WALL)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count<const W: usize, const H: usize>(state: &Grid<W, H>, material: u8) -> usize {
+        state.iter().flatten().filter(|&&c| c == material).count()
+    }
+
+    /// How tall the pile of `material` is in each column
+    fn heights(state: &Grid<16, 8>, material: u8) -> [usize; 16] {
+        let mut heights = [0; 16];
+        for row in state.iter() {
+            for (height, &cell) in heights.iter_mut().zip(row.iter()) {
+                *height += (cell == material) as usize;
+            }
+        }
+        heights
+    }
+
+    #[test]
+    fn grains_fall_to_the_floor() {
+        let mut state = scene::<3, 4>(["...", ".o.", "...", "..."]);
+        let mut rng = Rng::new(1);
+        step_state(&mut state, &mut rng);
+        assert_eq!(state, scene(["...", "...", ".o.", "..."]));
+        step_state(&mut state, &mut rng);
+        step_state(&mut state, &mut rng);
+        assert_eq!(state, scene(["...", "...", "...", ".o."]));
+    }
+
+    #[test]
+    fn grains_slide_off_each_other_but_not_off_walls() {
+        let mut state = scene::<3, 2>([".o.", ".o."]);
+        step_state(&mut state, &mut Rng::new(1));
+        assert!(state == scene(["...", "oo."]) || state == scene(["...", ".oo"]));
+
+        let mut state = scene::<3, 2>(["#o#", "#o#"]);
+        step_state(&mut state, &mut Rng::new(1));
+        assert_eq!(state, scene(["#o#", "#o#"]));
+    }
+
+    #[test]
+    fn sand_piles_up_at_the_angle_of_repose() {
+        let mut state = [[EMPTY; 16]; 8];
+        let mut rng = Rng::new(4);
+        let mut poured = 0;
+        while poured < 40 {
+            if state[0][8] == EMPTY {
+                state[0][8] = SAND;
+                poured += 1;
+            }
+            step_state(&mut state, &mut rng);
+        }
+        // finish falling without any more being poured in
+        for _ in 0..20 {
+            step_state(&mut state, &mut rng);
+        }
+
+        let heights = heights(&state, SAND);
+        for pair in heights.windows(2) {
+            assert!(pair[0].abs_diff(pair[1]) <= 1, "{:?}", heights);
+        }
+        // so it spreads out rather than standing up in a column
+        assert_eq!(heights.iter().sum::<usize>(), 40);
+        assert!(heights.iter().max() < Some(&8), "{:?}", heights);
+
+        // and once settled, sand stays put
+        let settled = state;
+        step_state(&mut state, &mut rng);
+        assert_eq!(state, settled);
+    }
+
+    #[test]
+    fn water_finds_its_level() {
+        let mut state = scene::<16, 8>([
+            "................",
+            "......~~~~......",
+            "......~~~~......",
+            "......~~~~......",
+            "......~~~~......",
+            "................",
+            "................",
+            "................",
+        ]);
+        let mut rng = Rng::new(2);
+        for _ in 0..200 {
+            step_state(&mut state, &mut rng);
+        }
+        assert_eq!(state[7], [WATER; 16]);
+        assert_eq!(heights(&state, WATER), [1; 16]);
+    }
+
+    #[test]
+    fn sand_sinks_through_water() {
+        let mut state = scene::<3, 4>([".o.", ".~.", "#~#", "#~#"]);
+        let mut rng = Rng::new(3);
+        for _ in 0..10 {
+            step_state(&mut state, &mut rng);
+        }
+        assert_eq!(state[3], scene::<3, 1>(["#o#"])[0]);
+        assert_eq!(count(&state, WATER), 3);
+    }
+
+    #[test]
+    fn nothing_is_made_or_lost() {
+        let mut state = [[EMPTY; 16]; 8];
+        let mut rng = Rng::new(11);
+        for cell in state.iter_mut().flatten() {
+            *cell = rng.below(4) as u8;
+        }
+        let walls = state.map(|row| row.map(|cell| cell == WALL));
+        let sand = count(&state, SAND);
+        let water = count(&state, WATER);
+
+        for _ in 0..200 {
+            step_state(&mut state, &mut rng);
+            assert_eq!(count(&state, SAND), sand);
+            assert_eq!(count(&state, WATER), water);
+            assert_eq!(state.map(|row| row.map(|cell| cell == WALL)), walls);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_result() {
+        let run = |seed| {
+            let mut state = Sand::SHELVES;
+            let mut rng = Rng::new(seed);
+            for generation in 0..100 {
+                if state[0][3] == EMPTY {
+                    state[0][3] = if generation % 3 == 0 { WATER } else { SAND };
+                }
+                step_state(&mut state, &mut rng);
+            }
+            state
+        };
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5), run(6));
+    }
+
+    #[test]
+    fn materials_have_their_own_brightness() {
+        let levels = [EMPTY, SAND, WATER, WALL].map(|m| Sand.brightness(m));
+        for (i, a) in levels.iter().enumerate() {
+            for b in &levels[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert_eq!(Sand.brightness(EMPTY), 0);
+    }
+}
@@ -0,0 +1,206 @@
+//! Life with several species of live cell
+//!
+//! Cells live, die and are born exactly as in Conway's Life, but every live
+//! cell belongs to a species, numbered from 1. A surviving cell keeps its
+//! species, and a newborn cell takes the species most of its three parents
+//! share. In QuadLife all three parents can be different, and then the
+//! newborn takes the one species none of them have.
+//!
+//! Immigration has two species and QuadLife has four. Dead cells are 0, so
+//! each species only needs the low bits of the cell, and the next generation
+//! goes in the high nibble the same way `step_state` does it.
+use crate::rng::Rng;
+use crate::{Grid, Palette, Topology, STATE};
+
+/// The most species a variant can have
+pub const MAX_SPECIES: u8 = 4;
+
+/// A multi-species variant of Life
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Species {
+    /// How many species there are
+    pub count: u8,
+}
+
+impl Species {
+    /// Two species
+    pub const IMMIGRATION: Species = Species::new(2);
+    /// Four species
+    pub const QUADLIFE: Species = Species::new(4);
+
+    /// A variant with `count` species, panicking if that is less than two or
+    /// more than `MAX_SPECIES`
+    pub const fn new(count: u8) -> Species {
+        if count < 2 || count > MAX_SPECIES {
+            panic!("invalid species count");
+        }
+        Species { count }
+    }
+
+    /// Find the species a newborn takes from its three parents' species
+    pub fn newborn(&self, parents: [u8; 3]) -> u8 {
+        let [a, b, c] = parents;
+        if a == b || a == c {
+            a
+        } else if b == c {
+            b
+        } else {
+            // every parent is different, which needs at least three species
+            (1..=self.count)
+                .find(|species| !parents.contains(species))
+                .unwrap_or(a)
+        }
+    }
+}
+
+impl Palette for Species {
+    /// Each species gets its own level, spread out so they are easy to tell
+    /// apart, with the first species brightest
+    fn brightness(&self, cell: u8) -> u8 {
+        if cell == 0 || cell > self.count {
+            0
+        } else {
+            15 - (cell - 1) * 12 / (self.count - 1)
+        }
+    }
+}
+
+/// Fill the grid with a random mix of dead cells and every species
+pub fn scatter<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    species: &Species,
+    rng: &mut Rng,
+) {
+    for cell in state.iter_mut().flatten() {
+        *cell = if rng.chance(1, 3) {
+            rng.below(species.count as u32) as u8 + 1
+        } else {
+            0
+        };
+    }
+}
+
+/// Advance the simulation by one generation
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    species: &Species,
+    topology: &Topology,
+) {
+    for row in 0..H {
+        for col in 0..W {
+            let cell = state[row][col] & STATE;
+
+            // note the first three live neighbors, since those are the
+            // parents if the cell is born
+            let mut parents = [0; 3];
+            let mut neighbors = 0;
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    if dr == 0 && dc == 0 {
+                        continue;
+                    }
+                    let r = row as isize + dr;
+                    let c = col as isize + dc;
+                    if let Some((r, c)) = topology.locate(r, c, W, H) {
+                        let neighbor = state[r][c] & STATE;
+                        if neighbor != 0 {
+                            if neighbors < 3 {
+                                parents[neighbors] = neighbor;
+                            }
+                            neighbors += 1;
+                        }
+                    }
+                }
+            }
+
+            let next = match (cell, neighbors) {
+                (0, 3) => species.newborn(parents),
+                (0, _) => 0,
+                (_, 2) | (_, 3) => cell,
+                _ => 0,
+            };
+            state[row][col] |= next << 4;
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rule;
+
+    #[test]
+    fn newborns_take_the_majority_species() {
+        let immigration = Species::IMMIGRATION;
+        assert_eq!(immigration.newborn([1, 1, 2]), 1);
+        assert_eq!(immigration.newborn([2, 1, 2]), 2);
+        assert_eq!(immigration.newborn([1, 2, 2]), 2);
+        assert_eq!(immigration.newborn([2, 2, 2]), 2);
+
+        let quadlife = Species::QUADLIFE;
+        assert_eq!(quadlife.newborn([3, 4, 3]), 3);
+        assert_eq!(quadlife.newborn([1, 2, 3]), 4);
+        assert_eq!(quadlife.newborn([4, 1, 2]), 3);
+        assert_eq!(quadlife.newborn([2, 3, 4]), 1);
+    }
+
+    #[test]
+    fn blinkers_keep_their_middle_and_pass_on_the_majority() {
+        let mut state = [[0; 5]; 5];
+        state[2][1..4].copy_from_slice(&[1, 2, 2]);
+        step_state(&mut state, &Species::IMMIGRATION, &Topology::Plane);
+        assert_eq!(state[1][2], 2);
+        assert_eq!(state[2][2], 2);
+        assert_eq!(state[3][2], 2);
+        assert_eq!(state[2][1], 0);
+
+        let mut state = [[0; 5]; 5];
+        state[1][2] = 1;
+        state[2][2] = 3;
+        state[3][2] = 4;
+        step_state(&mut state, &Species::QUADLIFE, &Topology::Plane);
+        assert_eq!(state[2][1..4], [2, 3, 2]);
+    }
+
+    #[test]
+    fn species_live_and_die_like_conway() {
+        let mut rng = Rng::new(1);
+        let mut state = [[0; 16]; 8];
+        scatter(&mut state, &Species::QUADLIFE, &mut rng);
+        let mut conway = state.map(|row| row.map(|cell| (cell != 0) as u8));
+
+        for _ in 0..30 {
+            step_state(&mut state, &Species::QUADLIFE, &Topology::Torus);
+            crate::step_state(&mut conway, &Rule::CONWAY, &Topology::Torus);
+            assert_eq!(state.map(|row| row.map(|cell| (cell != 0) as u8)), conway);
+        }
+    }
+
+    #[test]
+    fn scatter_uses_every_species() {
+        let mut state = [[0; 16]; 8];
+        scatter(&mut state, &Species::QUADLIFE, &mut Rng::new(2));
+        for species in 0..=4 {
+            assert!(state.iter().flatten().any(|&cell| cell == species));
+        }
+        assert!(state.iter().flatten().all(|&cell| cell <= 4));
+    }
+
+    #[test]
+    fn species_have_their_own_brightness() {
+        for species in [Species::IMMIGRATION, Species::QUADLIFE] {
+            let levels: Vec<u8> = (0..=species.count)
+                .map(|cell| species.brightness(cell))
+                .collect();
+            assert_eq!(levels[0], 0);
+            assert_eq!(levels[1], 15);
+            for pair in levels[1..].windows(2) {
+                assert!(pair[0] > pair[1] && pair[1] > 0, "{:?}", levels);
+            }
+        }
+    }
+}
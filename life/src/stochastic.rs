@@ -0,0 +1,211 @@
+//! Life where births and survivals only happen some of the time
+//!
+//! Each generation starts from what the rule says will happen. A cell the
+//! rule would bring to life is only born with the birth chance, and a live
+//! cell the rule would keep alive only survives with the survival chance,
+//! starting to die otherwise. After that, every cell is flipped with the
+//! noise chance, dead cells coming alive and everything else dying.
+//!
+//! All the randomness comes from the `Rng` passed in, so a run started from
+//! the same seed plays out the same way every time, on the host or on the
+//! device.
+use crate::rng::Rng;
+use crate::{next_cell, Grid, Rule, Topology, STATE};
+
+/// How likely something is, as a fraction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chance {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl Chance {
+    pub const ALWAYS: Chance = Chance::new(1, 1);
+    pub const NEVER: Chance = Chance::new(0, 1);
+
+    /// A chance of `numerator` in `denominator`, panicking if that is more
+    /// than certain or the denominator is zero
+    pub const fn new(numerator: u32, denominator: u32) -> Chance {
+        if denominator == 0 || numerator > denominator {
+            panic!("invalid chance");
+        }
+        Chance {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Decide whether it happens this time. Certain outcomes don't use up
+    /// any numbers, so a run that never leaves anything to chance leaves the
+    /// generator where it was.
+    pub fn happens(&self, rng: &mut Rng) -> bool {
+        if self.numerator == 0 {
+            false
+        } else if self.numerator == self.denominator {
+            true
+        } else {
+            rng.chance(self.numerator, self.denominator)
+        }
+    }
+}
+
+/// How often births, survivals and noise happen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stochastic {
+    /// How likely a cell the rule would bring to life is to be born
+    pub birth: Chance,
+    /// How likely a live cell the rule would keep alive is to survive
+    pub survival: Chance,
+    /// How likely each cell is to flip every generation
+    pub noise: Chance,
+}
+
+impl Stochastic {
+    /// Chances that leave the rule exactly as it is
+    pub const CERTAIN: Stochastic = Stochastic {
+        birth: Chance::ALWAYS,
+        survival: Chance::ALWAYS,
+        noise: Chance::NEVER,
+    };
+}
+
+/// Advance the simulation by one generation, leaving births, survivals and
+/// noise to chance
+pub fn step_state<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    rule: &Rule,
+    odds: &Stochastic,
+    topology: &Topology,
+    rng: &mut Rng,
+) {
+    for row in 0..H {
+        for col in 0..W {
+            let cell = state[row][col] & STATE;
+            let mut next = next_cell(state, rule, topology, row, col);
+
+            if cell == 0 && next == 1 && !odds.birth.happens(rng) {
+                next = 0;
+            } else if cell == 1 && next == 1 && !odds.survival.happens(rng) {
+                next = rule.advance(1, false, false);
+            }
+            if odds.noise.happens(rng) {
+                next = if next == 0 { 1 } else { 0 };
+            }
+
+            state[row][col] |= next << 4;
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{population, soup};
+
+    #[test]
+    fn certain_odds_follow_the_rule() {
+        for rule in [Rule::CONWAY, Rule::BRIANS_BRAIN] {
+            let mut expected: Grid<16, 8> = soup(1);
+            let mut state = expected;
+            let mut rng = Rng::new(1);
+            for _ in 0..20 {
+                crate::step_state(&mut expected, &rule, &Topology::Torus);
+                step_state(
+                    &mut state,
+                    &rule,
+                    &Stochastic::CERTAIN,
+                    &Topology::Torus,
+                    &mut rng,
+                );
+                assert_eq!(state, expected);
+            }
+            assert_eq!(rng, Rng::new(1));
+        }
+    }
+
+    #[test]
+    fn nothing_is_born_without_a_chance() {
+        let odds = Stochastic {
+            birth: Chance::NEVER,
+            ..Stochastic::CERTAIN
+        };
+        let mut state: Grid<16, 8> = soup(2);
+        let mut rng = Rng::new(2);
+        for _ in 0..20 {
+            let before = state;
+            step_state(&mut state, &Rule::CONWAY, &odds, &Topology::Torus, &mut rng);
+            for (&a, &b) in before.iter().flatten().zip(state.iter().flatten()) {
+                assert!(a == 1 || b == 0);
+            }
+        }
+    }
+
+    #[test]
+    fn cells_that_fail_to_survive_start_dying() {
+        let odds = Stochastic {
+            survival: Chance::NEVER,
+            ..Stochastic::CERTAIN
+        };
+        // a block would last forever, but here it dies at once
+        let mut state = [[0; 4]; 4];
+        for (r, c) in [(1, 1), (1, 2), (2, 1), (2, 2)] {
+            state[r][c] = 1;
+        }
+        let rule = Rule::new("B3/S23/3");
+        step_state(&mut state, &rule, &odds, &Topology::Plane, &mut Rng::new(3));
+        assert_eq!(state[1][1..3], [2, 2]);
+        assert_eq!(state[2][1..3], [2, 2]);
+    }
+
+    #[test]
+    fn chances_come_out_about_right() {
+        // a blinker's ends are born every generation, so with even odds of
+        // being born about half the births happen
+        let odds = Stochastic {
+            birth: Chance::new(1, 2),
+            ..Stochastic::CERTAIN
+        };
+        let mut rng = Rng::new(4);
+        let mut born = 0;
+        for _ in 0..1000 {
+            let mut state = [[0; 5]; 5];
+            state[2][1..4].copy_from_slice(&[1, 1, 1]);
+            step_state(&mut state, &Rule::CONWAY, &odds, &Topology::Plane, &mut rng);
+            born += (state[1][2] == 1) as u32 + (state[3][2] == 1) as u32;
+        }
+        assert!((900..1100).contains(&born), "{}", born);
+
+        // and noise flips about the right share of an empty grid
+        let odds = Stochastic {
+            noise: Chance::new(1, 10),
+            ..Stochastic::CERTAIN
+        };
+        let mut state = [[0; 16]; 8];
+        step_state(&mut state, &Rule::SEEDS, &odds, &Topology::Torus, &mut rng);
+        let flipped = population(&state);
+        assert!((5..25).contains(&flipped), "{}", flipped);
+    }
+
+    #[test]
+    fn the_same_seed_plays_out_the_same_way() {
+        let odds = Stochastic {
+            birth: Chance::new(9, 10),
+            survival: Chance::new(19, 20),
+            noise: Chance::new(1, 200),
+        };
+        let run = |seed| {
+            let mut state: Grid<16, 8> = soup(5);
+            let mut rng = Rng::new(seed);
+            for _ in 0..50 {
+                step_state(&mut state, &Rule::CONWAY, &odds, &Topology::Torus, &mut rng);
+            }
+            state
+        };
+        assert_eq!(run(6), run(6));
+        assert_ne!(run(6), run(7));
+    }
+}
@@ -0,0 +1,476 @@
+//! An effectively unbounded universe made of tiles from a fixed pool
+//!
+//! The universe is an endless plane, but only the parts of it with something
+//! going on are stored: it is cut into `TILE` by `TILE` squares, packed one
+//! bit per cell like a `PackedGrid`, and a tile is only taken from the pool
+//! when live cells get close enough to its edge that something could be born
+//! in it. Tiles that empty out go back to the pool.
+//!
+//! When the pool runs out, the missing tiles count as dead and anything that
+//! would have been born in them is lost, the same as at the edge of the
+//! plane. Everything already allocated carries on as before, and the tiles
+//! freed by patterns dying off can be used again.
+use crate::packed::{apply_rule, neighbor_counts, UnsupportedRule};
+use crate::{Grid, Neighborhood, Rule};
+
+/// How many cells there are along each side of a tile
+pub const TILE: usize = 16;
+
+/// The cells of the Gosper glider gun, as `(row, column)`, which fires a
+/// glider down and to the right every 30 generations
+pub const GOSPER_GLIDER_GUN: [(i32, i32); 36] = [
+    (0, 24),
+    (1, 22),
+    (1, 24),
+    (2, 12),
+    (2, 13),
+    (2, 20),
+    (2, 21),
+    (2, 34),
+    (2, 35),
+    (3, 11),
+    (3, 15),
+    (3, 20),
+    (3, 21),
+    (3, 34),
+    (3, 35),
+    (4, 0),
+    (4, 1),
+    (4, 10),
+    (4, 16),
+    (4, 20),
+    (4, 21),
+    (5, 0),
+    (5, 1),
+    (5, 10),
+    (5, 14),
+    (5, 16),
+    (5, 17),
+    (5, 22),
+    (5, 24),
+    (6, 10),
+    (6, 16),
+    (6, 24),
+    (7, 11),
+    (7, 15),
+    (8, 12),
+    (8, 13),
+];
+
+/// Every tile is in use, so a cell couldn't be set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolExhausted;
+
+/// One square of the universe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Tile {
+    /// Which tile this is, counted in tiles from the origin
+    row: i32,
+    col: i32,
+    /// The cells, with column `c` in bit `c` of each row
+    cells: [u16; TILE],
+    used: bool,
+}
+
+impl Tile {
+    const FREE: Tile = Tile {
+        row: 0,
+        col: 0,
+        cells: [0; TILE],
+        used: false,
+    };
+
+    /// Whether the tile has live cells on the edge or corner facing the
+    /// neighbor `dr` tiles down and `dc` tiles right, or anywhere if both
+    /// are 0
+    fn touches(&self, dr: i32, dc: i32) -> bool {
+        let rows = match dr {
+            -1 => &self.cells[..1],
+            1 => &self.cells[TILE - 1..],
+            _ => &self.cells[..],
+        };
+        let mask = match dc {
+            -1 => 1,
+            1 => 1 << (TILE - 1),
+            _ => !0,
+        };
+        rows.iter().any(|&row| row & mask != 0)
+    }
+}
+
+/// An unbounded universe with room for `N` tiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TiledUniverse<const N: usize> {
+    tiles: [Tile; N],
+    /// How many times a step needed a tile when the pool was empty
+    pub misses: u32,
+}
+
+impl<const N: usize> Default for TiledUniverse<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TiledUniverse<N> {
+    /// Make an empty universe
+    pub const fn new() -> Self {
+        TiledUniverse {
+            tiles: [Tile::FREE; N],
+            misses: 0,
+        }
+    }
+
+    pub fn get(&self, row: i32, col: i32) -> bool {
+        let ((tile_row, tile_col), (r, c)) = split(row, col);
+        match self.find(tile_row, tile_col) {
+            Some(index) => self.tiles[index].cells[r] & (1 << c) != 0,
+            None => false,
+        }
+    }
+
+    /// Set a cell, taking a tile from the pool if it needs one. Killing a
+    /// cell never needs one.
+    pub fn set(&mut self, row: i32, col: i32, alive: bool) -> Result<(), PoolExhausted> {
+        let ((tile_row, tile_col), (r, c)) = split(row, col);
+        let index = match self.find(tile_row, tile_col) {
+            Some(index) => index,
+            None if !alive => return Ok(()),
+            None => self.allocate(tile_row, tile_col).ok_or(PoolExhausted)?,
+        };
+        let cells = &mut self.tiles[index].cells;
+        if alive {
+            cells[r] |= 1 << c;
+        } else {
+            cells[r] &= !(1 << c);
+        }
+        Ok(())
+    }
+
+    /// Bring `cells` to life, offset by `top` rows and `left` columns
+    pub fn place(
+        &mut self,
+        cells: &[(i32, i32)],
+        top: i32,
+        left: i32,
+    ) -> Result<(), PoolExhausted> {
+        for &(row, col) in cells {
+            self.set(top + row, left + col, true)?;
+        }
+        Ok(())
+    }
+
+    /// Kill every cell and give every tile back to the pool
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Count the live cells
+    pub fn population(&self) -> u32 {
+        self.used()
+            .flat_map(|tile| tile.cells.iter())
+            .map(|row| row.count_ones())
+            .sum()
+    }
+
+    /// How many tiles are taken from the pool
+    pub fn tiles_in_use(&self) -> usize {
+        self.used().count()
+    }
+
+    /// Advance the simulation by one generation
+    ///
+    /// Only totalistic Moore rules with two states work on tiles, and rules
+    /// with B0 would fill the whole plane, so anything else is refused.
+    pub fn step(&mut self, rule: &Rule) -> Result<(), UnsupportedRule> {
+        if rule.states != 2
+            || rule.neighborhood != Neighborhood::Moore
+            || rule.isotropic.is_some()
+            || rule.birth & 1 != 0
+        {
+            return Err(UnsupportedRule);
+        }
+
+        // make sure every tile something could be born in is there first
+        for index in 0..N {
+            let tile = self.tiles[index];
+            if !tile.used {
+                continue;
+            }
+            for (dr, dc) in NEIGHBORS {
+                if !tile.touches(dr, dc) || self.find(tile.row + dr, tile.col + dc).is_some() {
+                    continue;
+                }
+                if self.allocate(tile.row + dr, tile.col + dc).is_none() {
+                    self.misses = self.misses.saturating_add(1);
+                }
+            }
+        }
+
+        let mut next = [[0; TILE]; N];
+        for (index, cells) in next.iter_mut().enumerate() {
+            if self.tiles[index].used {
+                *cells = self.next_tile(rule, index);
+            }
+        }
+        for (tile, cells) in self.tiles.iter_mut().zip(next.iter()) {
+            tile.cells = *cells;
+            if !tile.touches(0, 0) {
+                tile.used = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the `VW` by `VH` window with its top left corner at `top`, `left`
+    /// into a display image, the same way `show_state` does
+    pub fn show<const VW: usize, const VH: usize>(
+        &self,
+        top: i32,
+        left: i32,
+        image: &mut Grid<VW, VH>,
+    ) {
+        for (r, image_row) in image.iter_mut().enumerate() {
+            for (c, pixel) in image_row.iter_mut().enumerate() {
+                let alive = self.get(top + r as i32, left + c as i32);
+                *pixel = if alive { 15 } else { 0 };
+            }
+        }
+    }
+
+    /// Compute the next generation of one tile, using the edges of the tiles
+    /// around it
+    fn next_tile(&self, rule: &Rule, index: usize) -> [u16; TILE] {
+        let tile = &self.tiles[index];
+        let mut around = [[None; 3]; 3];
+        for (dr, row) in around.iter_mut().enumerate() {
+            for (dc, neighbor) in row.iter_mut().enumerate() {
+                *neighbor = self.find(tile.row + dr as i32 - 1, tile.col + dc as i32 - 1);
+            }
+        }
+        let cells = |dr: usize, dc: usize, r: usize| match around[dr][dc] {
+            Some(neighbor) => self.tiles[neighbor].cells[r],
+            None => 0,
+        };
+
+        // the rows from the one above the tile to the one below it, each
+        // lined up with its western and eastern neighbors as well
+        let mut west = [0u16; TILE + 2];
+        let mut middle = [0u16; TILE + 2];
+        let mut east = [0u16; TILE + 2];
+        for r in 0..TILE + 2 {
+            let dr = match r {
+                0 => 0,
+                _ if r == TILE + 1 => 2,
+                _ => 1,
+            };
+            let source = (r + TILE - 1) % TILE;
+            middle[r] = cells(dr, 1, source);
+            west[r] = (middle[r] << 1) | (cells(dr, 0, source) >> (TILE - 1));
+            east[r] = (middle[r] >> 1) | (cells(dr, 2, source) << (TILE - 1));
+        }
+
+        let mut next = [0; TILE];
+        for (r, row) in next.iter_mut().enumerate() {
+            let counts = neighbor_counts([
+                west[r],
+                middle[r],
+                east[r],
+                west[r + 1],
+                east[r + 1],
+                west[r + 2],
+                middle[r + 2],
+                east[r + 2],
+            ]);
+            *row = apply_rule(rule, middle[r + 1], counts);
+        }
+        next
+    }
+
+    fn used(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles.iter().filter(|tile| tile.used)
+    }
+
+    fn find(&self, row: i32, col: i32) -> Option<usize> {
+        self.tiles
+            .iter()
+            .position(|tile| tile.used && tile.row == row && tile.col == col)
+    }
+
+    /// Take an empty tile from the pool, if there are any left
+    fn allocate(&mut self, row: i32, col: i32) -> Option<usize> {
+        let index = self.tiles.iter().position(|tile| !tile.used)?;
+        self.tiles[index] = Tile {
+            row,
+            col,
+            cells: [0; TILE],
+            used: true,
+        };
+        Some(index)
+    }
+}
+
+/// The eight tiles around a tile, as rows down and columns right
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Find which tile a cell is in, and where it is in that tile
+fn split(row: i32, col: i32) -> ((i32, i32), (usize, usize)) {
+    let size = TILE as i32;
+    (
+        (row.div_euclid(size), col.div_euclid(size)),
+        (row.rem_euclid(size) as usize, col.rem_euclid(size) as usize),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{step_state, Topology};
+
+    const GLIDER: [(i32, i32); 5] = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+    #[test]
+    fn cells_anywhere_on_the_plane() {
+        let mut universe = TiledUniverse::<4>::new();
+        for &(row, col) in &[(0, 0), (-1, -1), (1000, -2000), (-17, 33)] {
+            universe.set(row, col, true).unwrap();
+            assert!(universe.get(row, col));
+        }
+        assert_eq!(universe.tiles_in_use(), 4);
+        assert_eq!(universe.set(500, 500, true), Err(PoolExhausted));
+        // killing a cell in a missing tile is fine
+        assert_eq!(universe.set(500, 500, false), Ok(()));
+        assert!(!universe.get(1, 1));
+        assert_eq!(universe.population(), 4);
+    }
+
+    #[test]
+    fn matches_step_state_across_tile_edges() {
+        // a soup on a plane big enough that nothing reaches its edges,
+        // straddling the tiles around the origin
+        let mut state = [[0; 64]; 64];
+        let mut universe = TiledUniverse::<32>::new();
+        let mut rng = crate::Rng::new(3);
+        for row in 24..40 {
+            for col in 24..40 {
+                if rng.chance(1, 3) {
+                    state[row][col] = 1;
+                    universe
+                        .set(row as i32 - 32, col as i32 - 32, true)
+                        .unwrap();
+                }
+            }
+        }
+        for generation in 0..40 {
+            step_state(&mut state, &Rule::CONWAY, &Topology::Plane);
+            universe.step(&Rule::CONWAY).unwrap();
+            for row in 0..64 {
+                for col in 0..64 {
+                    assert_eq!(
+                        universe.get(row as i32 - 32, col as i32 - 32),
+                        state[row][col] == 1,
+                        "generation {} at {}, {}",
+                        generation,
+                        row,
+                        col
+                    );
+                }
+            }
+        }
+        assert_eq!(universe.misses, 0);
+    }
+
+    #[test]
+    fn tiles_follow_a_glider_and_are_freed_behind_it() {
+        let mut universe = TiledUniverse::<4>::new();
+        universe.place(&GLIDER, -1, -1).unwrap();
+        // far further than a 64x32 torus would let it go before wrapping
+        for _ in 0..1000 {
+            universe.step(&Rule::CONWAY).unwrap();
+            assert_eq!(universe.population(), 5);
+            assert!(universe.tiles_in_use() <= 4);
+        }
+        assert_eq!(universe.misses, 0);
+
+        let mut image = [[0; 16]; 8];
+        universe.show(249 - 2, 249 - 2, &mut image);
+        let mut expected = [[0; 16]; 8];
+        for &(r, c) in &GLIDER {
+            expected[r as usize + 2][c as usize + 2] = 15;
+        }
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn refuses_rules_tiles_cannot_run() {
+        let mut universe = TiledUniverse::<4>::new();
+        universe.place(&GLIDER, 0, 0).unwrap();
+        let start = universe;
+        for rule in [
+            Rule::BRIANS_BRAIN,
+            Rule::new("B2/S34H"),
+            Rule::new("B3/S2-i34q"),
+            Rule::new("B0123/S1234"),
+        ] {
+            assert_eq!(universe.step(&rule), Err(UnsupportedRule), "{}", rule);
+            assert_eq!(universe, start);
+        }
+    }
+
+    #[test]
+    fn dead_patterns_give_their_tiles_back() {
+        let mut universe = TiledUniverse::<2>::new();
+        universe.set(0, 0, true).unwrap();
+        universe.set(100, 100, true).unwrap();
+        universe.step(&Rule::CONWAY).unwrap();
+        assert_eq!(universe.tiles_in_use(), 0);
+        universe.place(&GLIDER, 40, 40).unwrap();
+        assert_eq!(universe.tiles_in_use(), 1);
+    }
+
+    #[test]
+    fn a_glider_gun_runs_until_the_pool_runs_out() {
+        let mut universe = TiledUniverse::<16>::new();
+        universe.place(&GOSPER_GLIDER_GUN, 0, 0).unwrap();
+        let gun = |universe: &TiledUniverse<16>| {
+            let mut image = [[0; 36]; 9];
+            universe.show(0, 0, &mut image);
+            image
+        };
+        let start = gun(&universe);
+
+        // a new glider every 30 generations, for as long as there is room
+        let mut generation = 0;
+        while universe.misses == 0 {
+            universe.step(&Rule::CONWAY).unwrap();
+            generation += 1;
+            if generation % 30 == 0 {
+                assert_eq!(gun(&universe), start);
+                assert_eq!(universe.population(), 36 + 5 * (generation / 30));
+            }
+        }
+        assert!(generation > 300, "{}", generation);
+
+        // after that, gliders are lost off the edge of the tiles there are,
+        // but the gun keeps going
+        while generation % 30 != 0 {
+            universe.step(&Rule::CONWAY).unwrap();
+            generation += 1;
+        }
+        for _ in 0..20 {
+            for _ in 0..30 {
+                universe.step(&Rule::CONWAY).unwrap();
+            }
+            assert_eq!(gun(&universe), start);
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! Ways of joining the edges of the grid, named the way Golly names its
+//! bounded grids
+//!
+//! | Topology | Notation |
+//! |---|---|
+//! | plane | `P16,8` |
+//! | torus | `T16,8` |
+//! | Klein bottle, top and bottom twisted | `K16*,8` |
+//! | Klein bottle, left and right twisted | `K16,8*` |
+//! | cross-surface | `C16,8` |
+//! | sphere | `S8` |
+use crate::parse_number;
+
+/// How the edges of the grid are joined together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    /// Nothing is joined, so everything off the edge is dead
+    Plane,
+    /// Opposite edges are joined
+    Torus,
+    /// Opposite edges are joined, with one pair joined with a twist
+    Klein(Twist),
+    /// Opposite edges are joined, both with a twist. This is also known as
+    /// the real projective plane.
+    CrossSurface,
+    /// The top edge is joined to the left edge and the bottom edge to the
+    /// right edge. Only works on square grids.
+    Sphere,
+}
+
+/// Which pair of edges a Klein bottle flips when joining them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Twist {
+    /// Crossing the top or bottom edge mirrors the column
+    Horizontal,
+    /// Crossing the left or right edge mirrors the row
+    Vertical,
+}
+
+/// Reasons a topology string can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopologyError {
+    /// The string isn't shaped like `T16,8`
+    Malformed,
+    /// The size in the string doesn't match the grid
+    WrongSize,
+}
+
+impl Topology {
+    /// Parse Golly's bounded grid notation, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad topology becomes a compile
+    /// error.
+    pub const fn new(spec: &str, width: usize, height: usize) -> Topology {
+        match Topology::parse(spec, width, height) {
+            Ok(topology) => topology,
+            Err(_) => panic!("invalid topology"),
+        }
+    }
+
+    /// Parse Golly's bounded grid notation, checking the size against the
+    /// grid it will be used on
+    pub const fn parse(spec: &str, width: usize, height: usize) -> Result<Topology, TopologyError> {
+        let bytes = spec.as_bytes();
+        if bytes.is_empty() {
+            return Err(TopologyError::Malformed);
+        }
+
+        let (spec_width, i) = match parse_number(bytes, 1) {
+            Some(n) => n,
+            None => return Err(TopologyError::Malformed),
+        };
+        let width_twisted = i < bytes.len() && bytes[i] == b'*';
+        let i = if width_twisted { i + 1 } else { i };
+
+        // a sphere only has one size
+        if bytes[0].eq_ignore_ascii_case(&b'S') {
+            if i != bytes.len() || width_twisted {
+                return Err(TopologyError::Malformed);
+            }
+            if spec_width != width || spec_width != height {
+                return Err(TopologyError::WrongSize);
+            }
+            return Ok(Topology::Sphere);
+        }
+
+        if i >= bytes.len() || bytes[i] != b',' {
+            return Err(TopologyError::Malformed);
+        }
+        let (spec_height, i) = match parse_number(bytes, i + 1) {
+            Some(n) => n,
+            None => return Err(TopologyError::Malformed),
+        };
+        let height_twisted = i < bytes.len() && bytes[i] == b'*';
+        let i = if height_twisted { i + 1 } else { i };
+        if i != bytes.len() {
+            return Err(TopologyError::Malformed);
+        }
+
+        let topology = match (bytes[0].to_ascii_uppercase(), width_twisted, height_twisted) {
+            (b'P', false, false) => Topology::Plane,
+            (b'T', false, false) => Topology::Torus,
+            (b'K', true, false) => Topology::Klein(Twist::Horizontal),
+            (b'K', false, true) => Topology::Klein(Twist::Vertical),
+            (b'C', false, false) => Topology::CrossSurface,
+            _ => return Err(TopologyError::Malformed),
+        };
+
+        if spec_width != width || spec_height != height {
+            return Err(TopologyError::WrongSize);
+        }
+        Ok(topology)
+    }
+
+    /// Find the cell a possibly off-grid position refers to, or `None` if
+    /// there isn't one
+    ///
+    /// Positions can be any distance off the grid, wrapping round as many
+    /// times as it takes, with each twisted edge crossed flipping the other
+    /// axis again.
+    ///
+    /// Positions that are off the grid on both axes at once are only
+    /// resolved for the plane, torus and Klein bottle. On the cross-surface
+    /// and the sphere the corners are singular points, so their diagonal
+    /// neighbors across the corner are treated as dead.
+    ///
+    /// A sphere has to be square, and on any other grid everything off it is
+    /// treated as dead.
+    pub fn locate(
+        &self,
+        row: isize,
+        col: isize,
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let (w, h) = (width as isize, height as isize);
+        let row_inside = 0 <= row && row < h;
+        let col_inside = 0 <= col && col < w;
+        if row_inside && col_inside {
+            return Some((row as usize, col as usize));
+        }
+
+        match self {
+            Topology::Plane => None,
+            Topology::Torus => Some((row.rem_euclid(h) as usize, col.rem_euclid(w) as usize)),
+            Topology::Klein(Twist::Horizontal) => {
+                let c = flip_if_odd(col.rem_euclid(w), row.div_euclid(h), w);
+                Some((row.rem_euclid(h) as usize, c as usize))
+            }
+            Topology::Klein(Twist::Vertical) => {
+                let r = flip_if_odd(row.rem_euclid(h), col.div_euclid(w), h);
+                Some((r as usize, col.rem_euclid(w) as usize))
+            }
+            Topology::CrossSurface => {
+                if !row_inside && !col_inside {
+                    None
+                } else if row_inside {
+                    let r = flip_if_odd(row, col.div_euclid(w), h);
+                    Some((r as usize, col.rem_euclid(w) as usize))
+                } else {
+                    let c = flip_if_odd(col, row.div_euclid(h), w);
+                    Some((row.rem_euclid(h) as usize, c as usize))
+                }
+            }
+            Topology::Sphere => {
+                // only a square grid can have its edges joined this way
+                if width != height || (!row_inside && !col_inside) {
+                    return None;
+                }
+                // going straight across the sphere comes back to the start
+                // after twice its size, and within that, being past the
+                // bottom edge is the same as being above the top edge
+                let row = row.rem_euclid(2 * h);
+                let col = col.rem_euclid(2 * w);
+                if row >= h {
+                    // the bottom edge is joined to the right edge, and the
+                    // top edge to the left edge
+                    Some((col as usize, (2 * h - 1 - row) as usize))
+                } else if col >= w {
+                    Some(((2 * w - 1 - col) as usize, row as usize))
+                } else {
+                    Some((row as usize, col as usize))
+                }
+            }
+        }
+    }
+}
+
+/// Mirror a position along an axis of length `size` if a twisted edge was
+/// crossed an odd number of times to get there
+fn flip_if_odd(position: isize, crossings: isize, size: isize) -> isize {
+    if crossings & 1 == 0 {
+        position
+    } else {
+        size - 1 - position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_golly_notation() {
+        assert_eq!(Topology::parse("P16,8", 16, 8), Ok(Topology::Plane));
+        assert_eq!(Topology::parse("T16,8", 16, 8), Ok(Topology::Torus));
+        assert_eq!(
+            Topology::parse("K16*,8", 16, 8),
+            Ok(Topology::Klein(Twist::Horizontal))
+        );
+        assert_eq!(
+            Topology::parse("K16,8*", 16, 8),
+            Ok(Topology::Klein(Twist::Vertical))
+        );
+        assert_eq!(Topology::parse("C16,8", 16, 8), Ok(Topology::CrossSurface));
+        assert_eq!(Topology::parse("S8", 8, 8), Ok(Topology::Sphere));
+    }
+
+    #[test]
+    fn rejects_bad_notation() {
+        assert_eq!(Topology::parse("", 16, 8), Err(TopologyError::Malformed));
+        assert_eq!(Topology::parse("T16", 16, 8), Err(TopologyError::Malformed));
+        assert_eq!(
+            Topology::parse("T16*,8", 16, 8),
+            Err(TopologyError::Malformed)
+        );
+        assert_eq!(
+            Topology::parse("K16,8", 16, 8),
+            Err(TopologyError::Malformed)
+        );
+        assert_eq!(
+            Topology::parse("K16*,8*", 16, 8),
+            Err(TopologyError::Malformed)
+        );
+        assert_eq!(
+            Topology::parse("T16+2,8", 16, 8),
+            Err(TopologyError::Malformed)
+        );
+        assert_eq!(
+            Topology::parse("X16,8", 16, 8),
+            Err(TopologyError::Malformed)
+        );
+        assert_eq!(Topology::parse("S8,8", 8, 8), Err(TopologyError::Malformed));
+    }
+
+    #[test]
+    fn rejects_the_wrong_size() {
+        assert_eq!(
+            Topology::parse("T8,16", 16, 8),
+            Err(TopologyError::WrongSize)
+        );
+        assert_eq!(Topology::parse("S16", 16, 8), Err(TopologyError::WrongSize));
+    }
+
+    #[test]
+    fn plane_has_nothing_past_the_edge() {
+        let t = Topology::Plane;
+        assert_eq!(t.locate(3, 4, 16, 8), Some((3, 4)));
+        assert_eq!(t.locate(-1, 4, 16, 8), None);
+        assert_eq!(t.locate(3, 16, 16, 8), None);
+    }
+
+    #[test]
+    fn torus_wraps_straight_across() {
+        let t = Topology::Torus;
+        assert_eq!(t.locate(-1, 4, 16, 8), Some((7, 4)));
+        assert_eq!(t.locate(8, 4, 16, 8), Some((0, 4)));
+        assert_eq!(t.locate(3, -1, 16, 8), Some((3, 15)));
+        assert_eq!(t.locate(-1, 16, 16, 8), Some((7, 0)));
+    }
+
+    #[test]
+    fn klein_bottle_flips_one_axis() {
+        let t = Topology::Klein(Twist::Horizontal);
+        assert_eq!(t.locate(-1, 4, 16, 8), Some((7, 11)));
+        assert_eq!(t.locate(8, 0, 16, 8), Some((0, 15)));
+        assert_eq!(t.locate(3, -1, 16, 8), Some((3, 15)));
+
+        let t = Topology::Klein(Twist::Vertical);
+        assert_eq!(t.locate(-1, 4, 16, 8), Some((7, 4)));
+        assert_eq!(t.locate(2, -1, 16, 8), Some((5, 15)));
+        assert_eq!(t.locate(0, 16, 16, 8), Some((7, 0)));
+    }
+
+    #[test]
+    fn cross_surface_flips_both_axes() {
+        let t = Topology::CrossSurface;
+        assert_eq!(t.locate(-1, 4, 16, 8), Some((7, 11)));
+        assert_eq!(t.locate(2, 16, 16, 8), Some((5, 0)));
+        assert_eq!(t.locate(-1, -1, 16, 8), None);
+    }
+
+    #[test]
+    fn sphere_joins_adjacent_edges() {
+        let t = Topology::Sphere;
+        // above the top row is the left column
+        assert_eq!(t.locate(-1, 5, 8, 8), Some((5, 0)));
+        assert_eq!(t.locate(5, -1, 8, 8), Some((0, 5)));
+        // below the bottom row is the right column
+        assert_eq!(t.locate(8, 2, 8, 8), Some((2, 7)));
+        assert_eq!(t.locate(2, 8, 8, 8), Some((7, 2)));
+        assert_eq!(t.locate(-1, -1, 8, 8), None);
+        // a sphere that isn't square has nothing past its edges
+        assert_eq!(t.locate(-1, 10, 16, 8), None);
+        assert_eq!(t.locate(3, 16, 16, 8), None);
+        assert_eq!(t.locate(3, 10, 16, 8), Some((3, 10)));
+    }
+
+    /// Find a cell by crossing one edge at a time, the way the edges are
+    /// joined, until the position is back on the grid
+    fn cross_edges(
+        t: Topology,
+        row: isize,
+        col: isize,
+        w: isize,
+        h: isize,
+    ) -> Option<(usize, usize)> {
+        let (mut row, mut col) = (row, col);
+        loop {
+            let row_inside = 0 <= row && row < h;
+            let col_inside = 0 <= col && col < w;
+            if row_inside && col_inside {
+                return Some((row as usize, col as usize));
+            }
+            if !row_inside && !col_inside && matches!(t, Topology::CrossSurface | Topology::Sphere)
+            {
+                return None;
+            }
+            let (up, down) = (row + h, row - h);
+            let (left, right) = (col + w, col - w);
+            (row, col) = match t {
+                Topology::Plane => return None,
+                Topology::Torus if row < 0 => (up, col),
+                Topology::Torus if row >= h => (down, col),
+                Topology::Torus if col < 0 => (row, left),
+                Topology::Torus => (row, right),
+                Topology::Klein(Twist::Horizontal) | Topology::CrossSurface if row < 0 => {
+                    (up, w - 1 - col)
+                }
+                Topology::Klein(Twist::Horizontal) | Topology::CrossSurface if row >= h => {
+                    (down, w - 1 - col)
+                }
+                Topology::Klein(Twist::Horizontal) if col < 0 => (row, left),
+                Topology::Klein(Twist::Horizontal) => (row, right),
+                Topology::Klein(Twist::Vertical) if row < 0 => (up, col),
+                Topology::Klein(Twist::Vertical) if row >= h => (down, col),
+                Topology::Klein(Twist::Vertical) | Topology::CrossSurface if col < 0 => {
+                    (h - 1 - row, left)
+                }
+                Topology::Klein(Twist::Vertical) | Topology::CrossSurface => (h - 1 - row, right),
+                Topology::Sphere if row < 0 => (col, -row - 1),
+                Topology::Sphere if row >= h => (col, 2 * h - 1 - row),
+                Topology::Sphere if col < 0 => (-col - 1, row),
+                Topology::Sphere => (2 * w - 1 - col, row),
+            };
+        }
+    }
+
+    #[test]
+    fn wraps_any_number_of_times() {
+        let topologies = [
+            (Topology::Plane, 16, 8),
+            (Topology::Torus, 16, 8),
+            (Topology::Klein(Twist::Horizontal), 16, 8),
+            (Topology::Klein(Twist::Vertical), 16, 8),
+            (Topology::CrossSurface, 16, 8),
+            (Topology::Sphere, 8, 8),
+        ];
+        for &(t, w, h) in topologies.iter() {
+            for row in -3 * h..4 * h {
+                for col in -3 * w..4 * w {
+                    assert_eq!(
+                        t.locate(row, col, w as usize, h as usize),
+                        cross_edges(t, row, col, w, h),
+                        "{:?} ({}, {})",
+                        t,
+                        row,
+                        col
+                    );
+                }
+            }
+        }
+        // twice round the sphere is back where it started
+        assert_eq!(Topology::Sphere.locate(-16, 3, 8, 8), Some((0, 3)));
+        assert_eq!(
+            Topology::Klein(Twist::Horizontal).locate(-9, 4, 16, 8),
+            Some((7, 4))
+        );
+    }
+
+    #[test]
+    fn every_neighbor_relation_is_symmetric() {
+        // if b is a neighbor of a then a must be a neighbor of b, otherwise
+        // patterns would drift as they cross an edge
+        let topologies = [
+            (Topology::Plane, 16, 8),
+            (Topology::Torus, 16, 8),
+            (Topology::Klein(Twist::Horizontal), 16, 8),
+            (Topology::Klein(Twist::Vertical), 16, 8),
+            (Topology::CrossSurface, 16, 8),
+            (Topology::Sphere, 8, 8),
+        ];
+        for &(t, w, h) in topologies.iter() {
+            let neighbors = |row: usize, col: usize| {
+                let mut found = Vec::new();
+                for dr in -1..=1 {
+                    for dc in -1..=1 {
+                        if dr == 0 && dc == 0 {
+                            continue;
+                        }
+                        if let Some(n) = t.locate(row as isize + dr, col as isize + dc, w, h) {
+                            found.push(n);
+                        }
+                    }
+                }
+                found
+            };
+
+            for row in 0..h {
+                for col in 0..w {
+                    for (r, c) in neighbors(row, col) {
+                        let count_there =
+                            neighbors(r, c).iter().filter(|&&n| n == (row, col)).count();
+                        let count_here =
+                            neighbors(row, col).iter().filter(|&&n| n == (r, c)).count();
+                        assert_eq!(
+                            count_there, count_here,
+                            "{:?} ({}, {}) ({}, {})",
+                            t, row, col, r, c
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Langton's ant and other turmites
+//!
+//! A turmite is an ant walking over the grid. Each step it looks at the
+//! color of the cell it is on and its own state, then paints the cell a new
+//! color, turns, moves forward one cell, and switches state. The grid is a
+//! torus, wrapping the same way `count_neighbors_torus` does.
+//!
+//! Rules are written as compact strings. Ants that only have one state are
+//! written as one turn per color, so Langton's ant is `RL`: on color 0 turn
+//! right, on color 1 turn left, and either way step the color up by one. The
+//! turns are `L` and `R`, `N` for no turn and `U` for a U-turn.
+//!
+//! Turmites with more states list a transition for each color, separated by
+//! commas, with the states separated by slashes. Each transition is the color
+//! to write, the turn and the next state, so Langton's ant is also
+//! `1R0,0L0`, and `1R1,1L1/1R1,0N0` grows a square spiral. Colors past 9 are
+//! written as hex digits.
+use crate::{Grid, Palette, STATE};
+
+/// The most colors a turmite can use. Cells store their color in four bits.
+pub const MAX_COLORS: u8 = 16;
+
+/// The most states a turmite can have
+pub const MAX_STATES: u8 = 4;
+
+/// Which way an ant turns before it moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    None,
+    Right,
+    UTurn,
+    Left,
+}
+
+/// Which way an ant is facing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heading {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    /// The heading after turning
+    pub fn turn(self, turn: Turn) -> Heading {
+        let quarters = match turn {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        };
+        match (self as u8 + quarters) & 3 {
+            0 => Heading::North,
+            1 => Heading::East,
+            2 => Heading::South,
+            _ => Heading::West,
+        }
+    }
+}
+
+/// What an ant does on a cell of one color in one state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /// The color to paint the cell
+    pub write: u8,
+    pub turn: Turn,
+    /// The state to switch to
+    pub next: u8,
+}
+
+/// A turmite rule, stored as a table of transitions by state and color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Turmite {
+    pub colors: u8,
+    pub states: u8,
+    pub table: [[Transition; MAX_COLORS as usize]; MAX_STATES as usize],
+}
+
+/// One ant walking the grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ant {
+    pub row: usize,
+    pub col: usize,
+    pub heading: Heading,
+    pub state: u8,
+}
+
+/// Reasons a turmite string can fail to parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurmiteError {
+    /// The string isn't shaped like `RL` or `1R0,0L0`
+    Malformed,
+    /// There were more than `MAX_COLORS` colors, or the states didn't all
+    /// have the same number of them
+    BadColors,
+    /// There were more than `MAX_STATES` states, or a transition went to one
+    /// that doesn't exist
+    BadStates,
+}
+
+const UNUSED: Transition = Transition {
+    write: 0,
+    turn: Turn::None,
+    next: 0,
+};
+
+impl Turmite {
+    /// RL
+    pub const LANGTONS_ANT: Turmite = Turmite::new("RL");
+
+    /// Parse a turmite string, panicking if it is invalid
+    ///
+    /// This is meant for consts, where a bad turmite becomes a compile error.
+    pub const fn new(turmite: &str) -> Turmite {
+        match Turmite::parse(turmite) {
+            Ok(turmite) => turmite,
+            Err(_) => panic!("invalid turmite"),
+        }
+    }
+
+    /// Parse either a string of turns like `LLRR` or a full table of
+    /// transitions like `1R1,1L1/1R1,0N0`
+    pub const fn parse(turmite: &str) -> Result<Turmite, TurmiteError> {
+        let bytes = turmite.as_bytes();
+        let mut table = [[UNUSED; MAX_COLORS as usize]; MAX_STATES as usize];
+
+        let mut is_table = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            is_table |= bytes[i].is_ascii_digit();
+            i += 1;
+        }
+
+        if !is_table {
+            if bytes.is_empty() {
+                return Err(TurmiteError::Malformed);
+            }
+            if bytes.len() > MAX_COLORS as usize {
+                return Err(TurmiteError::BadColors);
+            }
+            let colors = bytes.len() as u8;
+            let mut color = 0;
+            while color < colors {
+                let turn = match parse_turn(bytes[color as usize]) {
+                    Some(turn) => turn,
+                    None => return Err(TurmiteError::Malformed),
+                };
+                table[0][color as usize] = Transition {
+                    write: (color + 1) % colors,
+                    turn,
+                    next: 0,
+                };
+                color += 1;
+            }
+            return Ok(Turmite {
+                colors,
+                states: 1,
+                table,
+            });
+        }
+
+        // transitions are three characters, then a comma between colors or
+        // a slash between states
+        let mut colors = 0;
+        let mut state = 0;
+        let mut color = 0;
+        let mut i = 0;
+        loop {
+            if i + 3 > bytes.len() {
+                return Err(TurmiteError::Malformed);
+            }
+            if state >= MAX_STATES {
+                return Err(TurmiteError::BadStates);
+            }
+            if color >= MAX_COLORS {
+                return Err(TurmiteError::BadColors);
+            }
+            let (write, turn, next) = (bytes[i], bytes[i + 1], bytes[i + 2]);
+            let turn = match parse_turn(turn) {
+                Some(turn) => turn,
+                None => return Err(TurmiteError::Malformed),
+            };
+            if !write.is_ascii_hexdigit() || !next.is_ascii_digit() {
+                return Err(TurmiteError::Malformed);
+            }
+            let write = match write {
+                b'0'..=b'9' => write - b'0',
+                _ => write.to_ascii_lowercase() - b'a' + 10,
+            };
+            table[state as usize][color as usize] = Transition {
+                write,
+                turn,
+                next: next - b'0',
+            };
+            color += 1;
+            i += 3;
+
+            if i == bytes.len() || bytes[i] == b'/' {
+                if state == 0 {
+                    colors = color;
+                } else if color != colors {
+                    return Err(TurmiteError::BadColors);
+                }
+                state += 1;
+                color = 0;
+                if i == bytes.len() {
+                    break;
+                }
+            } else if bytes[i] != b',' {
+                return Err(TurmiteError::Malformed);
+            }
+            i += 1;
+        }
+
+        // now that we know how big the table is, check it only refers to
+        // things inside it
+        let states = state;
+        let mut state = 0;
+        while state < states {
+            let mut color = 0;
+            while color < colors {
+                let transition = table[state as usize][color as usize];
+                if transition.write >= colors {
+                    return Err(TurmiteError::BadColors);
+                }
+                if transition.next >= states {
+                    return Err(TurmiteError::BadStates);
+                }
+                color += 1;
+            }
+            state += 1;
+        }
+
+        Ok(Turmite {
+            colors,
+            states,
+            table,
+        })
+    }
+}
+
+const fn parse_turn(byte: u8) -> Option<Turn> {
+    match byte.to_ascii_uppercase() {
+        b'N' => Some(Turn::None),
+        b'R' => Some(Turn::Right),
+        b'U' => Some(Turn::UTurn),
+        b'L' => Some(Turn::Left),
+        _ => None,
+    }
+}
+
+impl Palette for Turmite {
+    /// Colors get brighter as they go up, but stay dimmer than the ants
+    fn brightness(&self, cell: u8) -> u8 {
+        match cell {
+            0 => 0,
+            _ if cell < self.colors => (10 * cell).div_ceil(self.colors - 1),
+            _ => 0,
+        }
+    }
+}
+
+impl Ant {
+    /// An ant facing north in state 0
+    pub const fn new(row: usize, col: usize) -> Ant {
+        Ant {
+            row,
+            col,
+            heading: Heading::North,
+            state: 0,
+        }
+    }
+}
+
+/// Move every ant one step, one after another
+///
+/// An ant that lands on a cell another ant has just painted sees the new
+/// color, so the order of the ants matters when they meet.
+pub fn step_ants<const W: usize, const H: usize>(
+    state: &mut Grid<W, H>,
+    turmite: &Turmite,
+    ants: &mut [Ant],
+) {
+    for ant in ants.iter_mut() {
+        // colors left over from a turmite with more of them act like 0
+        let color = state[ant.row][ant.col] & STATE;
+        let color = if color < turmite.colors { color } else { 0 };
+        let transition = turmite.table[ant.state as usize][color as usize];
+
+        state[ant.row][ant.col] = transition.write;
+        ant.heading = ant.heading.turn(transition.turn);
+        ant.state = transition.next;
+
+        match ant.heading {
+            Heading::North => ant.row = (ant.row + H - 1) % H,
+            Heading::East => ant.col = (ant.col + 1) % W,
+            Heading::South => ant.row = (ant.row + 1) % H,
+            Heading::West => ant.col = (ant.col + W - 1) % W,
+        }
+    }
+}
+
+/// Write the grid into a display image with the ants at full brightness
+pub fn show_ants<const W: usize, const H: usize>(
+    state: &Grid<W, H>,
+    ants: &[Ant],
+    image: &mut Grid<W, H>,
+    turmite: &Turmite,
+) {
+    crate::show_state(state, image, turmite);
+    for ant in ants {
+        image[ant.row][ant.col] = 15;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_turn_strings() {
+        let ant = Turmite::parse("RL").unwrap();
+        assert_eq!((ant.colors, ant.states), (2, 1));
+        assert_eq!(
+            ant.table[0][0],
+            Transition {
+                write: 1,
+                turn: Turn::Right,
+                next: 0
+            }
+        );
+        assert_eq!(
+            ant.table[0][1],
+            Transition {
+                write: 0,
+                turn: Turn::Left,
+                next: 0
+            }
+        );
+
+        let llrr = Turmite::parse("llrr").unwrap();
+        assert_eq!(llrr.colors, 4);
+        assert_eq!(llrr.table[0][3].write, 0);
+        assert_eq!(llrr.table[0][2].turn, Turn::Right);
+        assert_eq!(Turmite::parse("NU").unwrap().table[0][1].turn, Turn::UTurn);
+    }
+
+    #[test]
+    fn parses_transition_tables() {
+        assert_eq!(Turmite::parse("1R0,0L0"), Ok(Turmite::LANGTONS_ANT));
+
+        let spiral = Turmite::parse("1R1,1L1/1R1,0N0").unwrap();
+        assert_eq!((spiral.colors, spiral.states), (2, 2));
+        assert_eq!(
+            spiral.table[1][1],
+            Transition {
+                write: 0,
+                turn: Turn::None,
+                next: 0
+            }
+        );
+        assert_eq!(
+            Turmite::parse("1R0,fL0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0,0N0")
+                .map(|t| (t.colors, t.table[0][1].write)),
+            Ok((16, 15))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_turmites() {
+        assert_eq!(Turmite::parse(""), Err(TurmiteError::Malformed));
+        assert_eq!(Turmite::parse("RX"), Err(TurmiteError::Malformed));
+        assert_eq!(
+            Turmite::parse("RLRLRLRLRLRLRLRLR"),
+            Err(TurmiteError::BadColors)
+        );
+        assert_eq!(Turmite::parse("1R0,0L"), Err(TurmiteError::Malformed));
+        assert_eq!(Turmite::parse("1R0;0L0"), Err(TurmiteError::Malformed));
+        assert_eq!(Turmite::parse("1R0,0L0/"), Err(TurmiteError::Malformed));
+        assert_eq!(Turmite::parse("2R0,0L0"), Err(TurmiteError::BadColors));
+        assert_eq!(Turmite::parse("1R1,0L0"), Err(TurmiteError::BadStates));
+        assert_eq!(Turmite::parse("1R1,0L0/1R0"), Err(TurmiteError::BadColors));
+        assert_eq!(
+            Turmite::parse("1R0/1R0/1R0/1R0/1R0"),
+            Err(TurmiteError::BadStates)
+        );
+    }
+
+    #[test]
+    fn langtons_ant_takes_its_first_steps() {
+        let mut state = [[0; 16]; 8];
+        let mut ants = [Ant::new(4, 8)];
+        let turmite = Turmite::LANGTONS_ANT;
+
+        // on empty cells it turns right every time, going round a square
+        for _ in 0..4 {
+            step_ants(&mut state, &turmite, &mut ants);
+        }
+        assert_eq!(state[4][8..10], [1, 1]);
+        assert_eq!(state[5][8..10], [1, 1]);
+        assert_eq!(ants[0], Ant::new(4, 8));
+
+        // then it finds its own trail and turns left
+        step_ants(&mut state, &turmite, &mut ants);
+        assert_eq!(state[4][8], 0);
+        assert_eq!((ants[0].row, ants[0].col), (4, 7));
+        assert_eq!(ants[0].heading, Heading::West);
+    }
+
+    #[test]
+    fn ants_wrap_around_the_torus() {
+        let mut state = [[0; 16]; 8];
+        let mut ants = [Ant::new(0, 0)];
+        step_ants(&mut state, &Turmite::new("N"), &mut ants);
+        assert_eq!((ants[0].row, ants[0].col), (7, 0));
+
+        let mut ants = [Ant {
+            heading: Heading::West,
+            ..Ant::new(0, 0)
+        }];
+        step_ants(&mut state, &Turmite::new("N"), &mut ants);
+        assert_eq!((ants[0].row, ants[0].col), (0, 15));
+    }
+
+    #[test]
+    fn langtons_ant_is_reversible() {
+        // turning the ant round and stepping back undoes its walk, which
+        // checks every cell it painted on the way out
+        let mut state = [[0; 16]; 8];
+        let mut ants = [Ant::new(3, 5), Ant::new(6, 12)];
+        let turmite = Turmite::new("RL");
+        for _ in 0..500 {
+            step_ants(&mut state, &turmite, &mut ants);
+        }
+        assert_ne!(state, [[0; 16]; 8]);
+
+        // step back: move back, then unpaint the cell and undo the turn
+        for _ in 0..500 {
+            for ant in ants.iter_mut().rev() {
+                ant.heading = ant.heading.turn(Turn::UTurn);
+                match ant.heading {
+                    Heading::North => ant.row = (ant.row + 7) % 8,
+                    Heading::East => ant.col = (ant.col + 1) % 16,
+                    Heading::South => ant.row = (ant.row + 1) % 8,
+                    Heading::West => ant.col = (ant.col + 15) % 16,
+                }
+                let color = (state[ant.row][ant.col] + 1) % 2;
+                state[ant.row][ant.col] = color;
+                let undo = match turmite.table[0][color as usize].turn {
+                    Turn::Right => Turn::Left,
+                    _ => Turn::Right,
+                };
+                ant.heading = ant.heading.turn(Turn::UTurn).turn(undo);
+            }
+        }
+        assert_eq!(state, [[0; 16]; 8]);
+        assert_eq!(ants, [Ant::new(3, 5), Ant::new(6, 12)]);
+    }
+
+    #[test]
+    fn ants_are_brighter_than_every_color() {
+        let turmite = Turmite::new("LLRRLRLR");
+        let mut last = 0;
+        for color in 1..turmite.colors {
+            let level = turmite.brightness(color);
+            assert!(last < level && level < 15, "{}", color);
+            last = level;
+        }
+
+        let mut state = [[0; 16]; 8];
+        state[2][2] = 7;
+        let mut image = [[0; 16]; 8];
+        show_ants(
+            &state,
+            &[Ant::new(2, 2), Ant::new(5, 9)],
+            &mut image,
+            &turmite,
+        );
+        assert_eq!(image[2][2], 15);
+        assert_eq!(image[5][9], 15);
+        assert_eq!(image[0][0], 0);
+    }
+}
@@ -0,0 +1,272 @@
+//! A window onto a universe bigger than the display
+//!
+//! The universe is a `PackedGrid`, which keeps a 64x32 universe down to 256
+//! bytes. A `Viewport` is the part of it shown on the display, and it can be
+//! panned by hand or left to move by itself: following the center of mass of
+//! the whole population, or tracking whatever is in view, so a spaceship that
+//! starts on the display stays on it.
+//!
+//! The window wraps round the edges of the universe, to suit a universe on
+//! the torus. On the plane, the window stops at the edges instead.
+use crate::packed::{PackedGrid, Row};
+use crate::Grid;
+
+/// How far past the window tracking looks, so cells that have just left it
+/// are still followed
+const TRACK_MARGIN: usize = 2;
+
+/// How the viewport moves by itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Follow {
+    /// It only moves when panned
+    Manual,
+    /// It keeps the whole population's center of mass in the middle
+    CenterOfMass,
+    /// It keeps whatever is in view in the middle
+    Track,
+}
+
+/// A `VW` by `VH` window onto a universe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport<const VW: usize, const VH: usize> {
+    /// The universe row at the top of the window
+    pub top: usize,
+    /// The universe column at the left of the window
+    pub left: usize,
+    pub follow: Follow,
+    /// Whether the window wraps round the universe's edges, or stops at them
+    pub wrap: bool,
+}
+
+impl<const VW: usize, const VH: usize> Viewport<VW, VH> {
+    pub const fn new(top: usize, left: usize, follow: Follow, wrap: bool) -> Viewport<VW, VH> {
+        Viewport {
+            top,
+            left,
+            follow,
+            wrap,
+        }
+    }
+
+    /// Move the window by `rows` down and `cols` right, within a `W` by `H`
+    /// universe
+    pub fn pan<const W: usize, const H: usize>(&mut self, rows: isize, cols: isize) {
+        self.top = Self::moved(self.top, rows, H, VH, self.wrap);
+        self.left = Self::moved(self.left, cols, W, VW, self.wrap);
+    }
+
+    /// Move the window so the given universe cell is in the middle
+    pub fn center_on<const W: usize, const H: usize>(&mut self, row: usize, col: usize) {
+        let (middle_row, middle_col) = self.middle::<W, H>();
+        let rows = row as isize - middle_row as isize;
+        let cols = col as isize - middle_col as isize;
+        self.pan::<W, H>(rows, cols);
+    }
+
+    /// The universe cell in the middle of the window
+    pub fn middle<const W: usize, const H: usize>(&self) -> (usize, usize) {
+        ((self.top + VH / 2) % H, (self.left + VW / 2) % W)
+    }
+
+    /// Move the window the way `follow` says to, after a step
+    pub fn update<T: Row, const W: usize, const H: usize>(
+        &mut self,
+        universe: &PackedGrid<T, W, H>,
+    ) {
+        let reach = match self.follow {
+            Follow::Manual => return,
+            Follow::CenterOfMass => (H, W),
+            Follow::Track => (VH / 2 + TRACK_MARGIN, VW / 2 + TRACK_MARGIN),
+        };
+        if let Some((rows, cols)) = self.center_of_mass(universe, reach) {
+            self.pan::<W, H>(rows, cols);
+        }
+    }
+
+    /// Find how far the center of mass of the live cells within `reach` of
+    /// the middle is from the middle, or `None` if there aren't any
+    ///
+    /// Distances are measured from the middle of the window, the short way
+    /// round if the universe wraps, so a population that straddles an edge
+    /// doesn't average out to somewhere on the far side.
+    pub fn center_of_mass<T: Row, const W: usize, const H: usize>(
+        &self,
+        universe: &PackedGrid<T, W, H>,
+        reach: (usize, usize),
+    ) -> Option<(isize, isize)> {
+        let (middle_row, middle_col) = self.middle::<W, H>();
+        let mut count = 0;
+        let mut rows = 0;
+        let mut cols = 0;
+        for row in 0..H {
+            let dr = self.offset(row, middle_row, H);
+            if dr.unsigned_abs() > reach.0 {
+                continue;
+            }
+            for col in 0..W {
+                let dc = self.offset(col, middle_col, W);
+                if dc.unsigned_abs() > reach.1 || !universe.get(row, col) {
+                    continue;
+                }
+                count += 1;
+                rows += dr;
+                cols += dc;
+            }
+        }
+        if count == 0 {
+            None
+        } else {
+            Some((rows / count, cols / count))
+        }
+    }
+
+    /// Copy the window into a display image, the same way `show_state` does
+    pub fn show<T: Row, const W: usize, const H: usize>(
+        &self,
+        universe: &PackedGrid<T, W, H>,
+        image: &mut Grid<VW, VH>,
+    ) {
+        for (r, image_row) in image.iter_mut().enumerate() {
+            for (c, pixel) in image_row.iter_mut().enumerate() {
+                let row = (self.top + r) % H;
+                let col = (self.left + c) % W;
+                *pixel = if universe.get(row, col) { 15 } else { 0 };
+            }
+        }
+    }
+
+    /// How far `to` is from `from` along an axis of length `size`
+    fn offset(&self, to: usize, from: usize, size: usize) -> isize {
+        let mut offset = to as isize - from as isize;
+        if self.wrap {
+            let size = size as isize;
+            if offset >= size / 2 {
+                offset -= size;
+            } else if offset < -size / 2 {
+                offset += size;
+            }
+        }
+        offset
+    }
+
+    /// Move a window edge along an axis of length `size`
+    fn moved(start: usize, by: isize, size: usize, window: usize, wrap: bool) -> usize {
+        let moved = start as isize + by;
+        if wrap {
+            moved.rem_euclid(size as isize) as usize
+        } else {
+            moved.clamp(0, size.saturating_sub(window) as isize) as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rule, Topology};
+
+    type Universe = PackedGrid<u64, 64, 32>;
+
+    fn glider(universe: &mut Universe, row: usize, col: usize) {
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            universe.set(row + r, col + c, true);
+        }
+    }
+
+    #[test]
+    fn shows_only_the_window() {
+        let mut universe = Universe::new();
+        universe.set(10, 20, true);
+        universe.set(0, 0, true);
+        let viewport = Viewport::<16, 8>::new(5, 10, Follow::Manual, true);
+        let mut image = [[9; 16]; 8];
+        viewport.show(&universe, &mut image);
+        let mut expected = [[0; 16]; 8];
+        expected[5][10] = 15;
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn windows_wrap_round_the_torus() {
+        let mut universe = Universe::new();
+        universe.set(0, 0, true);
+        universe.set(31, 63, true);
+        let viewport = Viewport::<16, 8>::new(28, 56, Follow::Manual, true);
+        let mut image = [[0; 16]; 8];
+        viewport.show(&universe, &mut image);
+        assert_eq!(image[3][7], 15);
+        assert_eq!(image[4][8], 15);
+    }
+
+    #[test]
+    fn panning_wraps_or_stops_at_the_edges() {
+        let mut wrapping = Viewport::<16, 8>::new(0, 0, Follow::Manual, true);
+        wrapping.pan::<64, 32>(-1, -2);
+        assert_eq!((wrapping.top, wrapping.left), (31, 62));
+        wrapping.pan::<64, 32>(33, 66);
+        assert_eq!((wrapping.top, wrapping.left), (0, 0));
+
+        let mut stopping = Viewport::<16, 8>::new(0, 0, Follow::Manual, false);
+        stopping.pan::<64, 32>(-1, -2);
+        assert_eq!((stopping.top, stopping.left), (0, 0));
+        stopping.pan::<64, 32>(100, 100);
+        assert_eq!((stopping.top, stopping.left), (24, 48));
+
+        stopping.center_on::<64, 32>(20, 30);
+        assert_eq!(stopping.middle::<64, 32>(), (20, 30));
+    }
+
+    #[test]
+    fn center_of_mass_goes_the_short_way_round() {
+        let mut universe = Universe::new();
+        // a block split across the corner of the torus
+        universe.set(31, 63, true);
+        universe.set(31, 0, true);
+        universe.set(0, 63, true);
+        universe.set(0, 0, true);
+
+        let mut viewport = Viewport::<16, 8>::new(10, 20, Follow::CenterOfMass, true);
+        viewport.update(&universe);
+        // the middle ends up on the block rather than the middle of the
+        // universe
+        let (row, col) = viewport.middle::<64, 32>();
+        assert!(row == 31 || row == 0, "{}", row);
+        assert!(col == 63 || col == 0, "{}", col);
+        let mut image = [[0; 16]; 8];
+        viewport.show(&universe, &mut image);
+        assert_eq!(image.iter().flatten().filter(|&&p| p == 15).count(), 4);
+    }
+
+    #[test]
+    fn nothing_to_follow_leaves_the_window_alone() {
+        let universe = Universe::new();
+        let mut viewport = Viewport::<16, 8>::new(3, 4, Follow::CenterOfMass, true);
+        viewport.update(&universe);
+        assert_eq!((viewport.top, viewport.left), (3, 4));
+    }
+
+    #[test]
+    fn tracking_keeps_a_glider_in_view() {
+        let mut universe = Universe::new();
+        glider(&mut universe, 12, 28);
+        // another glider far away, which center of mass would be pulled
+        // towards but tracking ignores
+        glider(&mut universe, 0, 0);
+
+        let mut viewport = Viewport::<16, 8>::new(9, 22, Follow::Track, true);
+        for _ in 0..200 {
+            universe.step(&Rule::CONWAY, &Topology::Torus).unwrap();
+            viewport.update(&universe);
+
+            let mut image = [[0; 16]; 8];
+            viewport.show(&universe, &mut image);
+            let shown = image.iter().flatten().filter(|&&p| p == 15).count();
+            assert_eq!(shown, 5);
+        }
+        // the glider has gone 50 cells down and right in that time, and the
+        // window went with it
+        let (row, col) = viewport.middle::<64, 32>();
+        assert!(viewport.offset(row, 13 + 50, 32).abs() <= 1, "{}", row);
+        assert!(viewport.offset(col, 29 + 50, 64).abs() <= 1, "{}", col);
+    }
+}
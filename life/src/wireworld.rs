@@ -0,0 +1,207 @@
+//! WireWorld, where electrons run along wires
+//!
+//! Every cell is empty, a conductor, an electron head or an electron tail.
+//! Heads become tails, tails become conductors again, and a conductor becomes
+//! a head when one or two of its eight neighbors are heads. Empty cells never
+//! change, so the wires stay put while the electrons move along them.
+//!
+//! Circuits can be drawn as text, one string per row, with `.` for empty
+//! cells, `#` for conductors, `@` for heads and `~` for tails.
+use crate::{count_neighbors, draw, Grid, Palette, Topology, STATE};
+
+pub const EMPTY: u8 = 0;
+/// Heads are the only cells with state 1, so the usual neighbor counts count
+/// heads
+pub const HEAD: u8 = 1;
+pub const TAIL: u8 = 2;
+pub const CONDUCTOR: u8 = 3;
+
+/// The WireWorld palette, and a home for the built-in circuits
+///
+/// The circuits are laid out for the 16x8 panel on the plane. Wires that
+/// touch the edges would join up with whatever is across from them on other
+/// topologies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireWorld;
+
+impl WireWorld {
+    /// An electron going round a loop, sending one down the wire every ten
+    /// generations
+    pub const CLOCK: Grid<16, 8> = circuit([
+        "................",
+        "................",
+        "..##@~..........",
+        ".#....#.........",
+        "..####.#########",
+        "................",
+        "................",
+        "................",
+    ]);
+
+    /// A clock sending electrons both ways through diodes. They get through
+    /// the one on the right, but the one on the left stops them.
+    pub const DIODE: Grid<16, 8> = circuit([
+        "......##@~......",
+        ".....#....#.....",
+        "......####......",
+        "..##...#...##...",
+        "###.########.###",
+        "..##.......##...",
+        "................",
+        "................",
+    ]);
+
+    /// Two clocks with periods of six and ten feeding an OR gate, so the
+    /// output gets an electron whenever either input does. Electrons that
+    /// arrive too close together merge into one.
+    pub const OR_GATE: Grid<16, 8> = circuit([
+        ".@~.............",
+        "#..#............",
+        ".##.####........",
+        "........#.......",
+        ".......#########",
+        ".####...#.......",
+        "#....###........",
+        ".##@~...........",
+    ]);
+}
+
+impl Palette for WireWorld {
+    fn brightness(&self, cell: u8) -> u8 {
+        match cell {
+            HEAD => 15,
+            TAIL => 6,
+            CONDUCTOR => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Find the state a cell will be in next generation from how many of its
+/// neighbors are heads
+pub fn next(state: u8, heads: u8) -> u8 {
+    match state {
+        HEAD => TAIL,
+        TAIL => CONDUCTOR,
+        CONDUCTOR if heads == 1 || heads == 2 => HEAD,
+        CONDUCTOR => CONDUCTOR,
+        _ => EMPTY,
+    }
+}
+
+/// Advance the circuit by one generation
+pub fn step_state<const W: usize, const H: usize>(state: &mut Grid<W, H>, topology: &Topology) {
+    for row in 0..H {
+        for col in 0..W {
+            let cell = state[row][col] & STATE;
+            // only conductors care about their neighbors
+            let heads = if cell == CONDUCTOR {
+                count_neighbors(state, topology, row, col)
+            } else {
+                0
+            };
+            state[row][col] |= next(cell, heads) << 4;
+        }
+    }
+
+    for cell in state.iter_mut().flatten() {
+        *cell >>= 4;
+    }
+}
+
+/// Draw a circuit from text, panicking if a row is the wrong length or has a
+/// character that isn't a cell
+///
+/// This is meant for consts, where a bad drawing becomes a compile error.
+pub const fn circuit<const W: usize, const H: usize>(rows: [&str; H]) -> Grid<W, H> {
+    draw(
+        rows,
+        &[(b'.', EMPTY), (b'#', CONDUCTOR), (b'@', HEAD), (b'~', TAIL)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The generations within `generations` where the cell is a head
+    fn pulses(mut state: Grid<16, 8>, row: usize, col: usize, generations: u32) -> Vec<u32> {
+        let mut found = Vec::new();
+        for generation in 0..generations {
+            if state[row][col] == HEAD {
+                found.push(generation);
+            }
+            step_state(&mut state, &Topology::Plane);
+        }
+        found
+    }
+
+    /// The circuit with every electron turned back into wire
+    fn without_electrons(mut state: Grid<16, 8>, rows: core::ops::Range<usize>) -> Grid<16, 8> {
+        for row in rows {
+            for cell in state[row].iter_mut() {
+                if *cell != EMPTY {
+                    *cell = CONDUCTOR;
+                }
+            }
+        }
+        state
+    }
+
+    #[test]
+    fn electrons_follow_the_wire() {
+        let mut state = circuit::<8, 1>(["~@######"]);
+        step_state(&mut state, &Topology::Plane);
+        assert_eq!(state, circuit(["#~@#####"]));
+        for _ in 0..6 {
+            step_state(&mut state, &Topology::Plane);
+        }
+        assert_eq!(state, circuit(["#######~"]));
+        step_state(&mut state, &Topology::Plane);
+        assert_eq!(state, circuit(["########"]));
+    }
+
+    #[test]
+    fn three_heads_are_too_many() {
+        let mut state = circuit::<3, 3>(["@..", "@#.", "@.."]);
+        step_state(&mut state, &Topology::Plane);
+        assert_eq!(state, circuit(["~..", "~#.", "~.."]));
+    }
+
+    #[test]
+    fn clock_ticks_every_ten_generations() {
+        assert_eq!(pulses(WireWorld::CLOCK, 4, 15, 60), [17, 27, 37, 47, 57]);
+    }
+
+    #[test]
+    fn diode_only_lets_electrons_through_one_way() {
+        assert_eq!(pulses(WireWorld::DIODE, 4, 15, 50), [13, 23, 33, 43]);
+        assert_eq!(pulses(WireWorld::DIODE, 4, 0, 200), []);
+        // the clock keeps going even though the blocked electrons pile up
+        assert_eq!(pulses(WireWorld::DIODE, 1, 5, 50), [3, 13, 23, 33, 43]);
+    }
+
+    #[test]
+    fn or_gate_passes_either_input() {
+        let top = without_electrons(WireWorld::OR_GATE, 5..8);
+        assert_eq!(pulses(top, 4, 15, 40), [16, 22, 28, 34]);
+        let bottom = without_electrons(WireWorld::OR_GATE, 0..3);
+        assert_eq!(pulses(bottom, 4, 15, 40), [18, 28, 38]);
+
+        // 38 and 40 merge, and so do 68 and 70
+        let both = pulses(WireWorld::OR_GATE, 4, 15, 80);
+        assert_eq!(both, [16, 22, 28, 34, 38, 46, 52, 58, 64, 68, 76]);
+    }
+
+    #[test]
+    fn states_have_their_own_brightness() {
+        let levels = [EMPTY, HEAD, TAIL, CONDUCTOR].map(|s| WireWorld.brightness(s));
+        for (i, a) in levels.iter().enumerate() {
+            for b in &levels[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert_eq!(WireWorld.brightness(HEAD), 15);
+        assert_eq!(WireWorld.brightness(EMPTY), 0);
+    }
+}
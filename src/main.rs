@@ -17,9 +17,249 @@ use nb::block;
 
 use matrix_display::*;
 
+use life::age::{start_ages, step_ages};
+use life::cyclic;
+use life::elementary::{self, Boundary, Seed};
+use life::lenia::{self, Field};
+use life::margolus;
+use life::packed::PackedGrid;
+use life::rle;
+use life::sand;
+use life::species;
+use life::stochastic;
+use life::tiled::GOSPER_GLIDER_GUN;
+use life::turmite::{show_ants, step_ants};
+use life::{
+    show_state, wireworld, AgeGradient, Ant, Chance, CyclicRule, Direction, Elementary, Fade,
+    Follow, Grid, Lenia, Margolus, Reversible, Rng, Rule, Sand, Species, Stochastic, TiledUniverse,
+    Topology, Turmite, Viewport, WireWorld,
+};
+
 type SPI = SPIMaster4<Sercom4Pad0<Pa12<PfD>>, Sercom4Pad2<Pb10<PfD>>, Sercom4Pad3<Pb11<PfD>>>;
 type LEDPin = Pa17<Output<OpenDrain>>;
 
+/// Size of the LED matrix. `show_state` only accepts a state of the same size
+/// as the display, so these have to match `LEDArray::array`.
+const WIDTH: usize = 16;
+const HEIGHT: usize = 8;
+
+/// The rule the simulation starts with
+const RULE: Rule = Rule::new("B3/S23");
+
+/// The pattern the simulation starts with, in RLE so patterns can be pasted
+/// in from other Life software
+const SEED: &str = "\
+#N Panel seed
+x = 9, y = 7, rule = B3/S23
+4bo$3bo$2o2bob3o$2b4o2bo$2o2bob3o$3bo$2bo!
+";
+
+/// Where the top left corner of the seed goes on the panel
+const SEED_TOP: usize = 1;
+const SEED_LEFT: usize = 1;
+
+/// Rules the main loop switches between, one after another
+const RULES: [Rule; 4] = [RULE, Rule::HIGHLIFE, Rule::STAR_WARS, Rule::DAY_AND_NIGHT];
+
+/// How many generations to run each rule for before switching
+const RULE_GENERATIONS: u32 = 200;
+
+/// Edges the main loop switches between, one after another
+const TOPOLOGIES: [Topology; 3] = [
+    Topology::new("T16,8", WIDTH, HEIGHT),
+    Topology::new("K16*,8", WIDTH, HEIGHT),
+    Topology::new("C16,8", WIDTH, HEIGHT),
+];
+
+/// How many generations to run on each topology before switching
+const TOPOLOGY_GENERATIONS: u32 = 600;
+
+/// Life runs with a little noise, which keeps the small panel from settling
+/// down into still lifes for good
+const LIFE_ODDS: Stochastic = Stochastic {
+    noise: Chance::new(1, 4000),
+    ..Stochastic::CERTAIN
+};
+
+/// How brightness changes as cells get older in rules with only live and dead
+/// cells. Generations rules show their dying states instead.
+const AGE_GRADIENT: AgeGradient = AgeGradient::FLASH;
+
+/// How many generations of Life to run before moving on to the next mode
+const LIFE_GENERATIONS: u32 = 1200;
+
+/// WireWorld circuits, one shown each time WireWorld comes round
+const CIRCUITS: [Grid<WIDTH, HEIGHT>; 3] = [WireWorld::CLOCK, WireWorld::DIODE, WireWorld::OR_GATE];
+
+/// How many generations to show each circuit for
+const CIRCUIT_GENERATIONS: u32 = 120;
+
+/// Turmites, one shown each time the turmite mode comes round
+const TURMITES: [Turmite; 3] = [
+    Turmite::LANGTONS_ANT,
+    Turmite::new("RLR"),
+    Turmite::new("LLRR"),
+];
+
+/// Where the ants start
+const ANTS: [Ant; 2] = [Ant::new(2, 4), Ant::new(5, 11)];
+
+/// How many generations to show each turmite for
+const TURMITE_GENERATIONS: u32 = 400;
+
+/// Elementary rules and their first generations, one shown each time the
+/// elementary mode comes round
+const ELEMENTARY: [(Elementary, Seed); 2] = [
+    (Elementary::new(30, Boundary::Wrap), Seed::Random),
+    (
+        Elementary::new(110, Boundary::Fixed),
+        Seed::Single(WIDTH - 1),
+    ),
+];
+
+/// How many generations to show each elementary rule for
+const ELEMENTARY_GENERATIONS: u32 = 200;
+
+/// Cyclic rules, one shown each time the cyclic mode comes round. Sixteen
+/// states use every brightness level the panel has.
+const CYCLIC: [CyclicRule; 2] = [CyclicRule::new("R1/T1/C16/NM"), CyclicRule::SPIRALS];
+
+/// How many generations to show each cyclic rule for
+const CYCLIC_GENERATIONS: u32 = 300;
+
+/// Block rules, one shown each time the block mode comes round
+const BLOCK_RULES: [Margolus; 3] = [Margolus::CRITTERS, Margolus::TRON, Margolus::BILLIARD_BALL];
+
+/// How many generations to show each block rule for
+const BLOCK_GENERATIONS: u32 = 300;
+
+/// Columns the sand mode pours into, one after another
+const POUR_COLUMNS: [usize; 2] = [3, 12];
+
+/// Pour sand into the sand mode this many times for every time water is
+/// poured
+const SAND_PER_WATER: u32 = 2;
+
+/// How many generations to show the sand mode for
+const SAND_GENERATIONS: u32 = 400;
+
+/// Multi-species variants, one shown each time the species mode comes round
+const SPECIES: [Species; 2] = [Species::IMMIGRATION, Species::QUADLIFE];
+
+/// How many generations to show each multi-species variant for
+const SPECIES_GENERATIONS: u32 = 300;
+
+/// The rule reversible Life runs
+const REVERSIBLE_RULE: Rule = Rule::CONWAY;
+
+/// How many generations to show reversible Life for. It runs forwards for
+/// the first half, then turns round and runs back to where it started, so
+/// this has to be even.
+const REVERSIBLE_GENERATIONS: u32 = 400;
+
+/// How many generations to show Lenia for
+const LENIA_GENERATIONS: u32 = 400;
+
+/// Size of the universe the panel is a window onto. A row of 64 cells packs
+/// into a `u64`, so the whole universe takes 256 bytes.
+const UNIVERSE_WIDTH: usize = 64;
+const UNIVERSE_HEIGHT: usize = 32;
+
+type Universe = PackedGrid<u64, UNIVERSE_WIDTH, UNIVERSE_HEIGHT>;
+
+/// How the window moves, one used each time the universe mode comes round.
+/// Windows that don't follow anything are panned along by hand.
+const FOLLOWS: [Follow; 3] = [Follow::Manual, Follow::CenterOfMass, Follow::Track];
+
+/// How many generations to show the universe for
+const UNIVERSE_GENERATIONS: u32 = 400;
+
+/// How many tiles the unbounded universe has. Each tile is 16x16 cells, so
+/// this is room for the glider gun and a stream of gliders a couple of
+/// hundred cells long.
+const TILES: usize = 24;
+
+/// Where the window onto the glider gun starts, as its top row and left
+/// column, with the gun's top left corner at the origin. This puts the
+/// gliders the gun fires along the middle of the window.
+const GUN_WINDOW: (i32, i32) = (2, 12);
+
+/// Gliders move a cell down and right every 4 generations, so the window
+/// moves with them that often
+const GLIDER_SPEED: u32 = 4;
+
+/// How many generations to show the glider gun for
+const UNBOUNDED_GENERATIONS: u32 = 1200;
+
+/// Which automaton is on the panel
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Life,
+    WireWorld,
+    Turmite,
+    Elementary,
+    Cyclic,
+    Block,
+    Sand,
+    Species,
+    Reversible,
+    Lenia,
+    Universe,
+    Unbounded,
+}
+
+impl Mode {
+    /// How many generations to show the mode for before moving on
+    fn generations(self) -> u32 {
+        match self {
+            Mode::Life => LIFE_GENERATIONS,
+            Mode::WireWorld => CIRCUIT_GENERATIONS,
+            Mode::Turmite => TURMITE_GENERATIONS,
+            Mode::Elementary => ELEMENTARY_GENERATIONS,
+            Mode::Cyclic => CYCLIC_GENERATIONS,
+            Mode::Block => BLOCK_GENERATIONS,
+            Mode::Sand => SAND_GENERATIONS,
+            Mode::Species => SPECIES_GENERATIONS,
+            Mode::Reversible => REVERSIBLE_GENERATIONS,
+            Mode::Lenia => LENIA_GENERATIONS,
+            Mode::Universe => UNIVERSE_GENERATIONS,
+            Mode::Unbounded => UNBOUNDED_GENERATIONS,
+        }
+    }
+}
+
+/// Modes the main loop switches between, one after another. Life picks up
+/// where it left off, while the others start over each time they come round.
+const MODES: [Mode; 22] = [
+    Mode::Life,
+    Mode::WireWorld,
+    Mode::Life,
+    Mode::Turmite,
+    Mode::Life,
+    Mode::Elementary,
+    Mode::Life,
+    Mode::Cyclic,
+    Mode::Life,
+    Mode::Block,
+    Mode::Life,
+    Mode::Sand,
+    Mode::Life,
+    Mode::Species,
+    Mode::Life,
+    Mode::Reversible,
+    Mode::Life,
+    Mode::Lenia,
+    Mode::Life,
+    Mode::Universe,
+    Mode::Life,
+    Mode::Unbounded,
+];
+
+/// How many scans each change on the display fades over. The display moves
+/// on a generation every `frame_duration` scans, so fades longer than that
+/// run into each other, which looks smoother still but blurs fast patterns.
+const FADE_FRAMES: u8 = 6;
+
 /// Delay struct compatible with both the feather m0 timer and the LED Matrix
 #[derive(Clone, Copy)]
 struct DelayHertz(u32);
@@ -106,99 +346,267 @@ fn setup() -> (
     (red_led, tc5, array)
 }
 
-fn count_neighbors_bounded(state: &[[u8; 16]; 8], row: usize, col: usize) -> u8 {
-    let mut total = 0;
-    for r in row.saturating_sub(1)..=core::cmp::min(7, row+1) {
-        for c in col.saturating_sub(1)..=core::cmp::min(15, col+1) {
-            if r == row && c == col {
-                continue;
-            }
-            total += state[r][c] & 1;
-        }
-    }
-    total
-}
-
-fn count_neighbors_torus(state: &[[u8; 16]; 8], row: usize, col: usize) -> u8 {
-    let mut total = 0;
-    for roff in 7..=9 {
-        for coff in 15..=17 {
-            if roff == 8 && coff == 16 {
-                continue;
-            }
-
-            let r = (row + roff) % 8;
-            let c = (col + coff) % 16;
-            total += state[r][c] & 1;
-        }
-    }
-    total
-}
-
-fn step_state(state: &mut [[u8; 16]; 8]) {
-    // we can't allocate, so use the second lowest bit to signify what will
-    // happen in the next iteration
-    for row in 0..8 {
-        for col in 0..16 {
-            let neighbors = count_neighbors_torus(&state, row, col);
-            if state[row][col] & 1 == 0 && neighbors == 3 {
-                // we are dead and have 3 live neighbors
-                state[row][col] |= 0b10;
-            } else if state[row][col] & 1 == 1 && (neighbors == 2 || neighbors == 3) {
-                // we are alive and have 2 or 3 live neighbors
-                state[row][col] |= 0b10;
-            } else {
-                // we should die
-                // the next bit is already 0
-            }
-        }
-    }
-
-    // shift all the bits down one
-    for row in 0..8 {
-        for col in 0..16 {
-            state[row][col] = state[row][col] >> 1;
-        }
-    }
-}
-
-fn show_state(state: &[[u8; 16]; 8], image: &mut [[u8; 16]; 8]) {
-    for row in 0..8 {
-        for col in 0..16 {
-            image[row][col] = if state[row][col] == 1 {15} else {0};
-        }
-    }
-}
-
 #[entry]
 fn main() -> ! {
     let (mut red_led, mut _timer, mut array) = setup();
 
-    let mut state = [
-        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        [0, 1, 1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0],
-        [0, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0],
-        [0, 1, 1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0],
-        [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-        [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-    ];
+    let mut state: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    // a seed that doesn't parse leaves the panel empty, until the noise in
+    // the rule brings it to life
+    let _ = rle::place(SEED, &mut state, SEED_TOP, SEED_LEFT);
 
     let base_scan_freq = DelayHertz(1000);
 
-    show_state(&state, &mut array.array);
+    let mut ages: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    start_ages(&state, &mut ages);
+    // each generation is drawn into `image`, and the fade moves the display
+    // towards it a scan at a time
+    let mut image: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut fade: Fade<WIDTH, HEIGHT> = Fade::new(FADE_FRAMES);
+    show_state(&ages, &mut image, &AGE_GRADIENT);
+    fade.start(&image);
 
     let frame_duration = 8;
     let mut frame_timeout = 100;
 
+    let mut rule = RULE;
+    let mut rule_index = 0;
+    let mut topology = TOPOLOGIES[0];
+    let mut topology_index = 0;
+    let mut generation = 0;
+
+    let mut mode = MODES[0];
+    let mut mode_index = 0;
+    let mut mode_generation = 0;
+    let mut circuit = CIRCUITS[0];
+    let mut circuit_index = 0;
+    let mut trail: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut ants = ANTS;
+    let mut turmite = TURMITES[0];
+    let mut turmite_index = 0;
+    let mut history: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut elementary = ELEMENTARY[0].0;
+    let mut elementary_index = 0;
+    let mut cells: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut cyclic = CYCLIC[0];
+    let mut cyclic_index = 0;
+    let mut blocks: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut block_rule = BLOCK_RULES[0];
+    let mut block_index = 0;
+    let mut particles = Sand::SHELVES;
+    let mut colonies: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut variant = SPECIES[0];
+    let mut species_index = 0;
+    let mut reversible = Reversible::new(&[[0; WIDTH]; HEIGHT]);
+    let mut direction = Direction::Forward;
+    let mut field: Field<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut levels: Grid<WIDTH, HEIGHT> = [[0; WIDTH]; HEIGHT];
+    let mut universe = Universe::new();
+    let mut viewport: Viewport<WIDTH, HEIGHT> = Viewport::new(0, 0, FOLLOWS[0], true);
+    let mut follow_index = 0;
+    let mut tiles: TiledUniverse<TILES> = TiledUniverse::new();
+    let mut window = GUN_WINDOW;
+    // there is nothing random to seed from, so every run shows the same
+    // sequence of random rows and noise
+    let mut rng = Rng::new(1);
+
     loop {
         if frame_timeout == 0 {
-            show_state(&state, &mut array.array);
-            step_state(&mut state);
             frame_timeout = frame_duration;
+            mode_generation += 1;
+
+            match mode {
+                Mode::Life => {
+                    // Generations rules need a whole byte per cell, so the
+                    // panel uses the byte grid rather than a packed one
+                    if rule.states == 2 {
+                        show_state(&ages, &mut image, &AGE_GRADIENT);
+                    } else {
+                        show_state(&state, &mut image, &rule);
+                    }
+                    stochastic::step_state(&mut state, &rule, &LIFE_ODDS, &topology, &mut rng);
+                    step_ages(&state, &mut ages);
+
+                    generation += 1;
+                    if generation % RULE_GENERATIONS == 0 {
+                        rule_index = (rule_index + 1) % RULES.len();
+                        rule = RULES[rule_index];
+                    }
+                    if generation % TOPOLOGY_GENERATIONS == 0 {
+                        topology_index = (topology_index + 1) % TOPOLOGIES.len();
+                        topology = TOPOLOGIES[topology_index];
+                    }
+                }
+                Mode::WireWorld => {
+                    // the circuits are drawn for the plane, whatever
+                    // topology Life is on
+                    show_state(&circuit, &mut image, &WireWorld);
+                    wireworld::step_state(&mut circuit, &Topology::Plane);
+                }
+                Mode::Turmite => {
+                    show_ants(&trail, &ants, &mut image, &turmite);
+                    step_ants(&mut trail, &turmite, &mut ants);
+                }
+                Mode::Elementary => {
+                    show_state(&history, &mut image, &elementary);
+                    elementary::step_history(&mut history, &elementary);
+                }
+                Mode::Cyclic => {
+                    show_state(&cells, &mut image, &cyclic);
+                    let before = cells;
+                    cyclic::step_state(&mut cells, &cyclic, &TOPOLOGIES[0]);
+                    // the panel is small enough that the waves often die
+                    // out, so start again from fresh noise when they do
+                    if cells == before {
+                        cyclic::scatter(&mut cells, &cyclic, &mut rng);
+                    }
+                }
+                Mode::Block => {
+                    show_state(&blocks, &mut image, &block_rule);
+                    // the blocks shift every other generation, and the panel
+                    // is torus shaped with even sides, so they always fit
+                    margolus::step_state(&mut blocks, &block_rule, mode_generation);
+                }
+                Mode::Sand => {
+                    show_state(&particles, &mut image, &Sand);
+                    let col = POUR_COLUMNS[mode_generation as usize % POUR_COLUMNS.len()];
+                    if particles[0][col] == sand::EMPTY {
+                        particles[0][col] = if mode_generation % (SAND_PER_WATER + 1) == 0 {
+                            sand::WATER
+                        } else {
+                            sand::SAND
+                        };
+                    }
+                    sand::step_state(&mut particles, &mut rng);
+                }
+                Mode::Species => {
+                    show_state(&colonies, &mut image, &variant);
+                    species::step_state(&mut colonies, &variant, &topology);
+                }
+                Mode::Reversible => {
+                    show_state(&reversible.current, &mut image, &REVERSIBLE_RULE);
+                    // the first frame shows the seed, so turning round in
+                    // place at the halfway frame shows the furthest
+                    // generation twice and leaves the seed for the last frame
+                    if mode_generation == REVERSIBLE_GENERATIONS / 2 {
+                        direction = direction.reversed();
+                    } else {
+                        reversible.step(&REVERSIBLE_RULE, &TOPOLOGIES[0], direction);
+                    }
+                }
+                Mode::Lenia => {
+                    lenia::quantize(&field, &mut levels);
+                    show_state(&levels, &mut image, &Lenia::PANEL);
+                    lenia::step_field(&mut field, &Lenia::PANEL);
+                }
+                Mode::Universe => {
+                    viewport.show(&universe, &mut image);
+                    universe.step(&Rule::CONWAY, &Topology::Torus).unwrap_or(());
+                    if viewport.follow == Follow::Manual {
+                        viewport.pan::<UNIVERSE_WIDTH, UNIVERSE_HEIGHT>(0, 1);
+                    } else {
+                        viewport.update(&universe);
+                    }
+                }
+                Mode::Unbounded => {
+                    tiles.show(window.0, window.1, &mut image);
+                    tiles.step(&Rule::CONWAY).unwrap_or(());
+                    // ride along with the gliders until there are no tiles
+                    // left for them, then go back to watch the gun, which
+                    // keeps firing
+                    if tiles.misses > 0 {
+                        window = GUN_WINDOW;
+                    } else if mode_generation % GLIDER_SPEED == 0 {
+                        window = (window.0 + 1, window.1 + 1);
+                    }
+                }
+            }
+
+            fade.start(&image);
+
+            if mode_generation == mode.generations() {
+                mode_index = (mode_index + 1) % MODES.len();
+                mode = MODES[mode_index];
+                mode_generation = 0;
+                match mode {
+                    Mode::Life => {}
+                    Mode::WireWorld => {
+                        circuit = CIRCUITS[circuit_index];
+                        circuit_index = (circuit_index + 1) % CIRCUITS.len();
+                    }
+                    Mode::Turmite => {
+                        trail = [[0; WIDTH]; HEIGHT];
+                        ants = ANTS;
+                        turmite = TURMITES[turmite_index];
+                        turmite_index = (turmite_index + 1) % TURMITES.len();
+                    }
+                    Mode::Elementary => {
+                        let (rule, seed) = ELEMENTARY[elementary_index];
+                        elementary = rule;
+                        elementary::start(&mut history, seed, &mut rng);
+                        elementary_index = (elementary_index + 1) % ELEMENTARY.len();
+                    }
+                    Mode::Cyclic => {
+                        cyclic = CYCLIC[cyclic_index];
+                        cyclic::scatter(&mut cells, &cyclic, &mut rng);
+                        cyclic_index = (cyclic_index + 1) % CYCLIC.len();
+                    }
+                    Mode::Block => {
+                        block_rule = BLOCK_RULES[block_index];
+                        for cell in blocks.iter_mut().flatten() {
+                            *cell = rng.chance(1, 4) as u8;
+                        }
+                        block_index = (block_index + 1) % BLOCK_RULES.len();
+                    }
+                    Mode::Sand => particles = Sand::SHELVES,
+                    Mode::Species => {
+                        variant = SPECIES[species_index];
+                        species::scatter(&mut colonies, &variant, &mut rng);
+                        species_index = (species_index + 1) % SPECIES.len();
+                    }
+                    Mode::Reversible => {
+                        let mut seed = [[0; WIDTH]; HEIGHT];
+                        for cell in seed.iter_mut().flatten() {
+                            *cell = rng.chance(1, 3) as u8;
+                        }
+                        reversible = Reversible::new(&seed);
+                        direction = Direction::Forward;
+                    }
+                    Mode::Lenia => lenia::scatter(&mut field, &mut rng),
+                    Mode::Universe => {
+                        viewport.follow = FOLLOWS[follow_index];
+                        follow_index = (follow_index + 1) % FOLLOWS.len();
+                        universe = Universe::new();
+                        if viewport.follow == Follow::Track {
+                            // a lone glider, for the window to chase
+                            let (row, col) = viewport.middle::<UNIVERSE_WIDTH, UNIVERSE_HEIGHT>();
+                            for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+                                universe.set(
+                                    (row + r) % UNIVERSE_HEIGHT,
+                                    (col + c) % UNIVERSE_WIDTH,
+                                    true,
+                                );
+                            }
+                        } else {
+                            for row in 0..UNIVERSE_HEIGHT {
+                                for col in 0..UNIVERSE_WIDTH {
+                                    universe.set(row, col, rng.chance(1, 3));
+                                }
+                            }
+                        }
+                    }
+                    Mode::Unbounded => {
+                        tiles.clear();
+                        // the gun only takes a few tiles, so there is
+                        // always room for it in an empty pool
+                        tiles.place(&GOSPER_GLIDER_GUN, 0, 0).unwrap_or(());
+                        window = GUN_WINDOW;
+                    }
+                }
+            }
         }
         frame_timeout -= 1;
+        fade.show(&mut array.array);
         array.scan(base_scan_freq).unwrap_or(());
         red_led.toggle();
     }